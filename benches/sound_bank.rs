@@ -0,0 +1,52 @@
+// cargo bench --bench sound_bank
+// what one jump sound costs, decoding the wav each time against playing it from the bank, counting
+// both setting it up and pulling every sample through like the output thread would
+use std::hint::black_box;
+use std::io::Cursor;
+use std::time::Instant;
+use rodio::{Decoder, Source};
+use art_fight::common::{AudioSystem, Mixer, SoundBank};
+
+const JUMP: &[u8] = include_bytes!("../src/dino_run/sound_files/jump.wav");
+const PLAYS: u32 = 2000;
+
+fn time_per_play(name: &str, mut play: impl FnMut()) {
+    for _ in 0..PLAYS / 10 {
+        play();
+    };
+    let start = Instant::now();
+    for _ in 0..PLAYS {
+        play();
+    };
+    let per_play = start.elapsed() / PLAYS;
+    println!("{:<32} {:>10.1} us per play", name, per_play.as_secs_f64() * 1e6);
+}
+
+fn drain(source: impl Source<Item = f32>) -> f32 {
+    source.fold(0.0, |sum, sample| sum + sample)
+}
+
+fn main() {
+    let mut bank = SoundBank::default();
+    let jump = bank.load(JUMP);
+
+    time_per_play("decode and play", || {
+        let decoder = Decoder::new(Cursor::new(black_box(JUMP))).unwrap();
+        black_box(drain(decoder.convert_samples::<f32>()));
+    });
+    time_per_play("play from the bank", || {
+        black_box(drain(bank.source(black_box(jump))));
+    });
+
+    // just what happens on the game's side of a play, with the voice cap keeping the sinks bounded
+    let mut audio = AudioSystem::silent();
+    let jump = audio.load_sound(JUMP);
+    audio.set_mixer(&Mixer {max_voices: 16, ..Mixer::default()});
+    time_per_play("AudioSystem::play (silent)", || {
+        audio.play(black_box(jump));
+    });
+
+    let start = Instant::now();
+    black_box(SoundBank::default().load(JUMP));
+    println!("{:<32} {:>10.1} us once", "loading into the bank", start.elapsed().as_secs_f64() * 1e6);
+}
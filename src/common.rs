@@ -1,6 +1,14 @@
 use bevy::prelude::*;
-use rodio::{Decoder, OutputStreamHandle, Sink, Source};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use rodio::source::Spatial;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use std::env;
+use std::fs;
 use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[macro_export]
 macro_rules! event_exists {
@@ -9,10 +17,29 @@ macro_rules! event_exists {
     };
 }
 
+// the value after `name` on the command line, given as either `name value` or `name=value`
+pub fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = env::args().collect();
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == name {
+            args.get(i + 1).cloned()
+        } else {
+            arg.strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(str::to_string)
+        }
+    })
+}
+
+pub fn has_arg(name: &str) -> bool {
+    env::args().any(|arg| arg == name)
+}
+
 pub struct MaterialWizard {
     handles: Vec<Handle<StandardMaterial>>
 } impl MaterialWizard {
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         materials: &mut ResMut<Assets<StandardMaterial>>, reference_material: StandardMaterial,
         saturation: f32, lightness: f32, alpha: f32, color_count: usize, emissive: f32, unlit: bool
@@ -49,6 +76,7 @@ pub struct MaterialWizard {
         }
     }
 
+    #[allow(dead_code)]
     pub fn basic(
         materials: &mut ResMut<Assets<StandardMaterial>>,
         saturation: f32, lightness: f32, alpha: f32, color_count: usize, unlit: bool
@@ -84,32 +112,420 @@ pub struct MaterialWizard {
 
 }
 
+// read on its own first so a file from another version can be told apart from a broken one
+#[derive(Deserialize)]
+struct VersionProbe {
+    version: u32
+}
+
+// for RON files with a `version` field, `F` being the whole file. Ok(None) means nothing's been saved
+// yet, a file that's unreadable or from another version is reported, moved to `.ron.bak` so the next
+// save doesn't lose it, and comes back as the reason it was turned down
+pub fn load_versioned<F: DeserializeOwned>(path: &Path, version: u32, what: &str) -> Result<Option<F>, String> {
+    let Ok(text) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    let parsed = ron::from_str::<VersionProbe>(&text)
+        .map_err(|e| format!("unreadable ({})", e))
+        .and_then(|probe| if probe.version == version {
+            ron::from_str(&text).map_err(|e| format!("corrupted ({})", e))
+        } else {
+            Err(format!("version {} is not {}", probe.version, version))
+        });
+    parsed.map(Some).inspect_err(|reason| {
+        let backup = path.with_extension("ron.bak");
+        eprintln!("Ignoring {} at {}: {}, moving it to {}", what, path.display(), reason, backup.display());
+        if let Err(e) = fs::rename(path, &backup) {
+            eprintln!("Couldn't move old {} aside: {}", what, e);
+        };
+    })
+}
+
+// pretty RON, versioned files pass their whole file with its `version`. written then renamed, so a
+// crash mid-save can't leave a truncated file
+pub fn save_ron<F: Serialize>(path: &Path, file: &F) -> Result<(), String> {
+    let text = ron::ser::to_string_pretty(file, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    };
+    let temp = path.with_extension("ron.tmp");
+    fs::write(&temp, text).map_err(|e| e.to_string())?;
+    fs::rename(&temp, path).map_err(|e| e.to_string())
+}
+
+// bus gains, 0 is silent and 1 is the files' own level, applied to AudioSystem whenever they change
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct Mixer {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub muted: bool,
+    pub max_voices: usize  // sound effects playing at once, the oldest is cut off to make room
+} impl Default for Mixer {
+    fn default() -> Self {
+        Self {master: 1.0, music: 1.0, sfx: 1.0, muted: false, max_voices: 16}
+    }
+} impl Mixer {
+
+    pub fn music_gain(&self) -> f32 {
+        if self.muted {0.0} else {self.master * self.music}
+    }
+
+    pub fn sfx_gain(&self) -> f32 {
+        if self.muted {0.0} else {self.master * self.sfx}
+    }
+}
+
+// per sound, on top of the sfx bus
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoundParams {
+    pub volume: f32,
+    pub pitch: f32,  // playback speed, so 2 is an octave up and twice as short
+    pub pan: Option<f32>  // -1 is hard left and 1 hard right, None leaves the sound as it was recorded
+} impl Default for SoundParams {
+    fn default() -> Self {
+        Self {volume: 1.0, pitch: 1.0, pan: None}
+    }
+}
+
+// the listener's ears, either side of the origin
+const LEFT_EAR: [f32; 3] = [-1.0, 0.0, 0.0];
+const RIGHT_EAR: [f32; 3] = [1.0, 0.0, 0.0];
+
+// mixed down to mono and placed between the ears, the same model rodio's SpatialSink uses, but on a plain
+// sink so panned sounds still go through the voice cap and the silent backend
+pub fn panned<S>(source: S, pan: f32) -> Spatial<S> where S: Source, S::Item: rodio::Sample {
+    Spatial::new(source, [pan.clamp(-1.0, 1.0), 0.0, 0.0], LEFT_EAR, RIGHT_EAR)
+}
+
+struct Voice {
+    sink: Sink,
+    volume: f32
+}
+
+// which sound in a SoundBank, handed out when it's loaded
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SoundId(usize);
+
+// a whole sound decoded up front, shared by every play of it
+#[derive(Clone)]
+struct CachedSound {
+    samples: Arc<[f32]>,  // interleaved
+    channels: u16,
+    sample_rate: u32
+} impl CachedSound {
+
+    fn decode(sound_data: &'static[u8]) -> Result<Self, String> {
+        let decoder = Decoder::new(Cursor::new(sound_data)).map_err(|e| e.to_string())?;
+        let channels = decoder.channels();
+        let sample_rate = decoder.sample_rate();
+        Ok(Self {samples: decoder.convert_samples::<f32>().collect(), channels, sample_rate})
+    }
+}
+
+// one play of a CachedSound, just a position into the shared samples
+pub struct CachedSource {
+    sound: CachedSound,
+    position: usize
+} impl Iterator for CachedSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.sound.samples.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.sound.samples.len().saturating_sub(self.position);
+        (left, Some(left))
+    }
+} impl Source for CachedSource {
+
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.sound.samples.len().saturating_sub(self.position))
+    }
+
+    fn channels(&self) -> u16 {
+        self.sound.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sound.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.sound.samples.len() as u64 / self.sound.channels.max(1) as u64;
+        Some(Duration::from_secs_f64(frames as f64 / self.sound.sample_rate.max(1) as f64))
+    }
+}
+
+// sound effects decoded once when loaded, so playing one doesn't re-parse the file every time
+#[derive(Default)]
+pub struct SoundBank {
+    sounds: Vec<CachedSound>
+} impl SoundBank {
+
+    // a file that won't decode becomes silence rather than taking the game down with it
+    pub fn load(&mut self, sound_data: &'static[u8]) -> SoundId {
+        let sound = CachedSound::decode(sound_data).unwrap_or_else(|e| {
+            eprintln!("Couldn't decode a sound ({}), it'll be silent", e);
+            CachedSound {samples: Arc::from([]), channels: 1, sample_rate: 44100}
+        });
+        self.sounds.push(sound);
+        SoundId(self.sounds.len() - 1)
+    }
+
+    pub fn source(&self, id: SoundId) -> CachedSource {
+        CachedSource {sound: self.sounds[id.0].clone(), position: 0}
+    }
+}
+
+// one looping layer of the soundtrack, every stem plays at once and the game sets how loud each is
+#[derive(Clone, Copy, Debug)]
+pub struct MusicStem {
+    pub data: &'static[u8],
+    pub speed: f32  // like SoundParams::pitch
+}
+
+struct Stem {
+    sink: Sink,
+    gain: f32,
+    target: f32,
+    rate: f32  // gain per second on the way to target
+}
+
+// moves `value` toward `target` by at most `step`
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {(value + step).min(target)} else {(value - step).max(target)}
+}
+
+pub const MUTE_ARG: &str = "--mute";
+
+// with no audio_handle it's the null backend: sinks are idle and never reach a device, but everything
+// else, the voice cap and music fades included, behaves the same
 #[derive(Resource)]
 pub struct AudioSystem {
-    pub audio_handle: OutputStreamHandle,
-    pub music_sink: Sink
+    pub audio_handle: Option<OutputStreamHandle>,
+    stems: Vec<Stem>,
+    sting: Option<Sink>,  // a one-off on the music bus, not ducked or faded
+    duck: f32,  // music gain while ducked, climbing back to 1
+    duck_rate: f32,
+    voices: Vec<Voice>,  // oldest first
+    sounds: SoundBank,
+    mixer: Mixer
 } impl AudioSystem {
 
     pub fn new(audio_handle: OutputStreamHandle) -> Self {
-        let music_sink = Sink::try_new(&audio_handle).unwrap();
+        if let Err(e) = Sink::try_new(&audio_handle) {
+            eprintln!("Couldn't start audio playback ({}), carrying on without sound", e);
+            return Self::silent();
+        };
+        Self {audio_handle: Some(audio_handle), ..Self::silent()}
+    }
+
+    pub fn silent() -> Self {
         Self {
-            audio_handle,
-            music_sink
+            audio_handle: None,
+            stems: Vec::new(),
+            sting: None,
+            duck: 1.0,
+            duck_rate: 0.0,
+            voices: Vec::new(),
+            sounds: SoundBank::default(),
+            mixer: Mixer::default()
+        }
+    }
+
+    // the default output device, falling back to silence if there isn't one or `--mute` was given,
+    // the stream has to be kept alive for as long as there's sound
+    pub fn from_env() -> (Option<OutputStream>, Self) {
+        if has_arg(MUTE_ARG) {
+            return (None, Self::silent());
+        };
+        match OutputStream::try_default() {
+            Ok((stream, handle)) => (Some(stream), Self::new(handle)),
+            Err(e) => {
+                eprintln!("No audio output device ({}), carrying on without sound", e);
+                (None, Self::silent())
+            }
+        }
+    }
+
+    fn new_sink(&self) -> Option<Sink> {
+        match &self.audio_handle {
+            Some(handle) => Sink::try_new(handle).ok(),
+            None => Some(Sink::new_idle().0)
         }
     }
 
-    pub fn play_sound(&self, sound_data: &'static[u8]) {
-        let sink = Sink::try_new(&self.audio_handle).unwrap();
-        let cursor = Cursor::new(sound_data);
-        let source = Decoder::new(cursor).unwrap();
-        sink.append(source);
-        sink.detach()
+    // sounds already playing follow the new gains too
+    pub fn set_mixer(&mut self, mixer: &Mixer) {
+        self.mixer = mixer.clone();
+        self.apply_music_volumes();
+        for voice in &self.voices {
+            voice.sink.set_volume(voice.volume * mixer.sfx_gain());
+        };
+    }
+
+    pub fn voice_count(&mut self) -> usize {
+        self.voices.retain(|voice| !voice.sink.empty());
+        self.voices.len()
+    }
+
+    pub fn load_sound(&mut self, sound_data: &'static[u8]) -> SoundId {
+        self.sounds.load(sound_data)
+    }
+
+    pub fn play(&mut self, id: SoundId) {
+        self.play_with(id, SoundParams::default());
+    }
+
+    pub fn play_with(&mut self, id: SoundId, params: SoundParams) {
+        if self.mixer.muted || self.mixer.max_voices == 0 {
+            return;
+        };
+        while self.voice_count() >= self.mixer.max_voices {
+            self.voices.remove(0).sink.stop();
+        };
+        let Some(sink) = self.new_sink() else {
+            return;
+        };
+        sink.set_volume(params.volume * self.mixer.sfx_gain());
+        sink.set_speed(params.pitch);
+        let source = self.sounds.source(id);
+        match params.pan {
+            Some(pan) => sink.append(panned(source, pan)),
+            None => sink.append(source)
+        };
+        self.voices.push(Voice {sink, volume: params.volume});
+    }
+
+    // starts every stem from the top together so they stay in time, at `levels` (missing ones are silent)
+    pub fn play_music(&mut self, stems: &[MusicStem], levels: &[f32]) {
+        for stem in self.stems.drain(..) {
+            stem.sink.stop();
+        };
+        for (i, stem) in stems.iter().enumerate() {
+            let Some(sink) = self.new_sink() else {
+                continue;
+            };
+            let source = match Decoder::new(Cursor::new(stem.data)) {
+                Ok(decoder) => decoder.repeat_infinite(),
+                Err(e) => {
+                    eprintln!("Couldn't decode a music stem ({}), leaving it out", e);
+                    continue;
+                }
+            };
+            sink.set_speed(stem.speed);
+            sink.append(source);
+            let gain = levels.get(i).copied().unwrap_or(0.0);
+            self.stems.push(Stem {sink, gain, target: gain, rate: 0.0});
+        };
+        self.apply_music_volumes();
+    }
+
+    // a stem going all the way from silent to full takes `secs`, shorter moves take less, and 0 cuts
+    pub fn set_music_levels(&mut self, levels: &[f32], secs: f32) {
+        for (i, stem) in self.stems.iter_mut().enumerate() {
+            stem.target = levels.get(i).copied().unwrap_or(0.0);
+            if secs > 0.0 {
+                stem.rate = 1.0 / secs;
+            } else {
+                stem.gain = stem.target;
+            };
+        };
+        self.apply_music_volumes();
+    }
+
+    pub fn fade_out_music(&mut self, secs: f32) {
+        self.set_music_levels(&[], secs);
+    }
+
+    // drops the music by `depth` at once, then brings it back up over `secs`
+    pub fn duck_music(&mut self, depth: f32, secs: f32) {
+        if secs <= 0.0 {
+            return;
+        };
+        self.duck = self.duck.min(1.0 - depth.clamp(0.0, 1.0));
+        self.duck_rate = (1.0 - self.duck) / secs;
+        self.apply_music_volumes();
+    }
+
+    // played once over whatever the stems are doing, replacing any sting still ringing, and faded out
+    // after `max_secs` if it's still going by then
+    pub fn play_sting(&mut self, sting: MusicStem, max_secs: f32) {
+        self.stop_sting();
+        let Some(sink) = self.new_sink() else {
+            return;
+        };
+        match Decoder::new(Cursor::new(sting.data)) {
+            Ok(decoder) => {
+                // in the file's own time, which runs `speed` times faster than the clock
+                let mut clip = decoder.take_duration(Duration::from_secs_f32((max_secs * sting.speed).max(0.0)));
+                clip.set_filter_fadeout();
+                sink.append(clip);
+            },
+            Err(e) => {
+                eprintln!("Couldn't decode a music sting ({})", e);
+                return;
+            }
+        };
+        sink.set_speed(sting.speed);
+        sink.set_volume(self.mixer.music_gain());
+        self.sting = Some(sink);
+    }
+
+    pub fn stop_sting(&mut self) {
+        if let Some(sting) = self.sting.take() {
+            sting.stop();
+        };
+    }
+
+    pub fn sting_playing(&self) -> bool {
+        self.sting.as_ref().is_some_and(|sting| !sting.empty())
     }
 
-    pub fn play_music(&self, sound_data: &'static[u8]) {
-        self.music_sink.stop();
-        let cursor = Cursor::new(sound_data);
-        let source = Decoder::new(cursor).unwrap().repeat_infinite();
-        self.music_sink.append(source);
+    // moves the fades and ducking on by `delta` seconds
+    pub fn update_music(&mut self, delta: f32) {
+        for stem in &mut self.stems {
+            stem.gain = approach(stem.gain, stem.target, stem.rate * delta);
+        };
+        self.duck = approach(self.duck, 1.0, self.duck_rate * delta);
+        self.apply_music_volumes();
     }
-}
\ No newline at end of file
+
+    fn apply_music_volumes(&self) {
+        let gain = self.mixer.music_gain();
+        for stem in &self.stems {
+            stem.sink.set_volume(stem.gain * self.duck * gain);
+        };
+        if let Some(sting) = &self.sting {
+            sting.set_volume(gain);
+        };
+    }
+
+    // what each stem is actually playing at, bus gains and ducking included
+    pub fn music_volumes(&self) -> Vec<f32> {
+        self.stems.iter().map(|stem| stem.sink.volume()).collect()
+    }
+
+    pub fn pause_music(&self) {
+        for stem in &self.stems {
+            stem.sink.pause();
+        };
+        if let Some(sting) = &self.sting {
+            sting.pause();
+        };
+    }
+
+    pub fn resume_music(&self) {
+        for stem in &self.stems {
+            stem.sink.play();
+        };
+        if let Some(sting) = &self.sting {
+            sting.play();
+        };
+    }
+}
@@ -1,4 +1,19 @@
 pub mod mechanics;
 pub mod character;
 pub mod environment;
-pub mod audio;
\ No newline at end of file
+pub mod audio;
+pub mod state;
+pub mod input;
+pub mod stats;
+pub mod high_scores;
+pub mod hud;
+pub mod menu;
+pub mod settings;
+pub mod difficulty;
+pub mod config;
+pub mod seed;
+pub mod replay;
+pub mod visuals;
+pub mod headless;
+pub mod collision;
+pub mod obstacles;
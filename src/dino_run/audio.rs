@@ -1,41 +1,185 @@
+use std::f32::consts::FRAC_PI_2;
 use bevy::prelude::*;
-use crate::dino_run::mechanics::{PlayerHurt, PlayerScores, PlayerJumps};
-use crate::common::AudioSystem;
+use crate::dino_run::config::DinoRunConfig;
+use crate::dino_run::mechanics::{LevelSpeed, PlayerHurt, PlayerScores, PlayerJumps};
+use crate::dino_run::stats::RunStats;
+use crate::common::{AudioSystem, Mixer, MusicStem, SoundId, SoundParams};
+use fastrand::Rng;
 
 const SOUND_TRACK: &[u8] = include_bytes!("sound_files/crystal.wav");
 const HURT: &[u8] = include_bytes!("sound_files/hurt.wav");
 const JUMP: &[u8] = include_bytes!("sound_files/jump.wav");
 const SCORE: &[u8] = include_bytes!("sound_files/score.wav");
 
+// calm to frantic, the one track there is and the same an octave up, which loops twice as fast and so
+// stays in time with it, until there are real stems
+const MUSIC_STEMS: [MusicStem; 2] = [
+    MusicStem {data: SOUND_TRACK, speed: 1.0},
+    MusicStem {data: SOUND_TRACK, speed: 2.0}
+];
+const GAME_OVER_STING: MusicStem = MusicStem {data: SOUND_TRACK, speed: 0.5};
+const GAME_OVER_STING_SECS: f32 = 1.5;  // cut short, so a long track can stand in until there's a real sting
+const CROSSFADE_SECS: f32 = 2.0;
+const HURT_DUCK: f32 = 0.6;  // how much quieter the music drops on a hit
+const HURT_DUCK_SECS: f32 = 0.8;  // and how long it takes to come back
+const GAME_OVER_FADE_SECS: f32 = 1.5;
+const PITCH_JITTER: f32 = 0.03;  // +/- fraction of normal speed, about half a semitone
+const VOLUME_JITTER: f32 = 0.15;  // up to this much quieter
+const COMBO_SEMITONES: u32 = 12;  // each clear in a streak is a semitone above the last, up to an octave
+// world units either side of the dino that count as hard left or right, about the playfield ahead of it so
+// clears just behind the dino and hits just in front only lean a little off centre
+const PAN_RANGE: f32 = 15.0;
+
+// equal power between the two stems either side of `intensity`, so the overall loudness holds steady
+fn stem_levels(intensity: f32) -> [f32; MUSIC_STEMS.len()] {
+    let position = intensity.clamp(0.0, 1.0) * (MUSIC_STEMS.len() - 1) as f32;
+    std::array::from_fn(|i| {
+        let distance = (position - i as f32).abs();
+        if distance < 1.0 {(distance * FRAC_PI_2).cos()} else {0.0}
+    })
+}
+
+// the sound effects, decoded when the game starts
+#[derive(Resource)]
+pub struct DinoRunSounds {
+    pub jump: SoundId,
+    pub score: SoundId,
+    pub hurt: SoundId,
+    rng: Rng  // its own, so sound never draws from the run's seeded one
+} impl DinoRunSounds {
+
+    // a little different every time so repeats don't sound mechanical
+    fn varied(&mut self) -> SoundParams {
+        SoundParams {
+            volume: 1.0 - self.rng.f32() * VOLUME_JITTER,
+            pitch: 1.0 + (self.rng.f32() * 2.0 - 1.0) * PITCH_JITTER,
+            pan: None
+        }
+    }
+}
+
+// 1 for the first clear of a streak
+fn combo_pitch(combo: u32) -> f32 {
+    2.0_f32.powf(combo.saturating_sub(1).min(COMBO_SEMITONES) as f32 / 12.0)
+}
+
+// the camera looks along +y, so +x is to the right
+pub fn obstacle_pan(x: f32) -> f32 {
+    (x / PAN_RANGE).clamp(-1.0, 1.0)
+}
+
 pub fn setup_audio(
+    mut commands: Commands,
+    mut my_audio: ResMut<AudioSystem>
+) {
+    commands.insert_resource(
+        DinoRunSounds {
+            jump: my_audio.load_sound(JUMP),
+            score: my_audio.load_sound(SCORE),
+            hurt: my_audio.load_sound(HURT),
+            rng: Rng::new()
+        }
+    );
+    my_audio.play_music(&MUSIC_STEMS, &stem_levels(0.0));
+}
+
+pub fn apply_mixer(
+    mixer: Res<Mixer>,
+    mut audio_system: ResMut<AudioSystem>
+) {
+    audio_system.set_mixer(&mixer);
+}
+
+pub fn pause_music(
+    my_audio: Res<AudioSystem>
+) {
+    my_audio.pause_music();
+}
+
+pub fn resume_music(
     my_audio: Res<AudioSystem>
 ) {
-    my_audio.play_music(SOUND_TRACK);
+    my_audio.resume_music();
+}
+
+// fades stay put while paused, virtual time being stopped
+pub fn update_music(
+    time: Res<Time>,
+    mut my_audio: ResMut<AudioSystem>
+) {
+    my_audio.update_music(time.delta_secs());
+}
+
+// the faster the run, the further the mix leans toward the frantic stems
+pub fn adapt_music(
+    speed: Res<LevelSpeed>,
+    config: Res<DinoRunConfig>,
+    mut my_audio: ResMut<AudioSystem>
+) {
+    let intensity = config.difficulty.intensity(speed.f32);
+    my_audio.set_music_levels(&stem_levels(intensity), CROSSFADE_SECS);
+}
+
+pub fn duck_music(
+    mut event_reader: EventReader<PlayerHurt>,
+    mut my_audio: ResMut<AudioSystem>
+) {
+    event_reader.clear();
+    my_audio.duck_music(HURT_DUCK, HURT_DUCK_SECS);
+}
+
+pub fn game_over_music(
+    mut my_audio: ResMut<AudioSystem>
+) {
+    my_audio.fade_out_music(GAME_OVER_FADE_SECS);
+    my_audio.play_sting(GAME_OVER_STING, GAME_OVER_STING_SECS);
+}
+
+// so it doesn't ring on into the next run while the stems fade back in
+pub fn stop_sting(
+    mut my_audio: ResMut<AudioSystem>
+) {
+    my_audio.stop_sting();
 }
 
 pub fn jump_audio(
     mut event_reader: EventReader<PlayerJumps>,
-    audio_system: Res<AudioSystem>
+    mut audio_system: ResMut<AudioSystem>,
+    mut sounds: ResMut<DinoRunSounds>
 ) {
     for _ in event_reader.read(){
-        audio_system.play_sound(JUMP);
+        let params = sounds.varied();
+        audio_system.play_with(sounds.jump, params);
     };
 }
 
 pub fn score_audio(
     mut event_reader: EventReader<PlayerScores>,
-    audio_system: Res<AudioSystem>
+    mut audio_system: ResMut<AudioSystem>,
+    mut sounds: ResMut<DinoRunSounds>,
+    stats: Res<RunStats>
 ) {
-    for _ in event_reader.read(){
-        audio_system.play_sound(SCORE);
+    // the stats have already counted this frame's clears, so work back to where each one came in the streak
+    let scores: Vec<&PlayerScores> = event_reader.read().collect();
+    let first = (stats.streak + 1).saturating_sub(scores.len() as u32);
+    for (i, score) in scores.iter().enumerate() {
+        // no pitch jitter, it would blur the steps
+        let params = SoundParams {
+            pitch: combo_pitch(first + i as u32),
+            pan: Some(obstacle_pan(score.x)),
+            ..sounds.varied()
+        };
+        audio_system.play_with(sounds.score, params);
     };
 }
 
 pub fn hurt_audio(
     mut event_reader: EventReader<PlayerHurt>,
-    audio_system: Res<AudioSystem>
+    mut audio_system: ResMut<AudioSystem>,
+    mut sounds: ResMut<DinoRunSounds>
 ) {
-    for _ in event_reader.read(){
-        audio_system.play_sound(HURT);
+    for hurt in event_reader.read(){
+        let params = SoundParams {pan: Some(obstacle_pan(hurt.x)), ..sounds.varied()};
+        audio_system.play_with(sounds.hurt, params);
     };
-}
\ No newline at end of file
+}
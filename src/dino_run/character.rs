@@ -1,16 +1,12 @@
 use bevy::prelude::*;
 use std::f32::consts::{SQRT_2, PI, FRAC_PI_2};
 use bevy::pbr::{NotShadowCaster, NotShadowReceiver};
-use crate::dino_run::mechanics::{LevelSpeed, Player, JUMP_V};
+use crate::dino_run::mechanics::{LevelSpeed, Player, DUCK_HIP_DROP, DUCK_HEAD_DROP};
+use crate::dino_run::config::DinoRunConfig;
 
 pub const PITCH_CONSTANT: f32 = SQRT_2 / 2.0;
 const GREY: f32 = 0.4;
 
-#[derive(Resource)]
-pub struct AnimationState {
-    pub jumping: bool
-}
-
 #[derive(Component, Copy, Clone, PartialEq, Eq)]
 pub enum LegPart {
     Hip,
@@ -79,27 +75,29 @@ pub fn spawn_legs(
             hip_entity = joint_entity;
         };
     };
-    commands.insert_resource(AnimationState{jumping: false});
     hip_entity
 }
 
 pub fn animate_legs(
     mut query: Query<(&mut Transform, &LegPart)>,
     player_query: Query<&Player>,
-    animation_state: Res<AnimationState>,
     time: Res<Time>,
-    speed: Res<LevelSpeed>
+    speed: Res<LevelSpeed>,
+    config: Res<DinoRunConfig>
 ) {
+    let Ok(player) = player_query.single() else {
+        return;
+    };
     let leg_length = 1.2;
     let step_height = 0.5;
     let hip_splay = 0.25;
     let bone_length = leg_length / 2.0;
     let (
         left_foot, right_foot, left_knee, right_knee, hip, left_hip, right_hip
-    ) = if animation_state.jumping {
-        let v = player_query.single().unwrap().velocity;
+    ) = if player.airborne {
+        let v = player.velocity;
         let hip_height = leg_length;
-        let jump_normal = (v / JUMP_V).abs();
+        let jump_normal = (v / config.jump_v).abs();
         let foot_height = step_height - (jump_normal * step_height);
         let hip = Vec3::new(0.2, 0.0, hip_height);
         let mut left_hip = hip;
@@ -117,11 +115,15 @@ pub fn animate_legs(
         let t = time.elapsed_secs() * (speed.f32 / (step_distance * 2.0));
         let t1 = t % 2.0;
         let t2 = (t + 1.0) % 2.0;
-        let step_height = 0.5;
+        // a crouched scuttle, the knees bend further as the hip comes down
+        let step_height = if player.ducking {0.3} else {0.5};
         let left_foot = calculate_foot_pos(t1, step_distance, step_height, -hip_splay);
         let right_foot = calculate_foot_pos(t2, step_distance, step_height, hip_splay);
         let mut hip = calculate_hip_pos(t, leg_length, step_distance);
         hip.x += 0.2;
+        if player.ducking {
+            hip.z -= DUCK_HIP_DROP;
+        };
         let mut left_hip = hip;
         let mut right_hip = hip;
         left_hip.y -= hip_splay;
@@ -190,7 +192,7 @@ fn calculate_knee_pos(
         projection_pos.z -= projection_angle.sin() * projection_length;
         projection_pos.y = foot_pos.y;
     };
-    return projection_pos;
+    projection_pos
 }
 
 #[derive(Component, Copy, Clone, PartialEq, Eq)]
@@ -205,6 +207,7 @@ const TAIL_STEP: f32 = 0.25;
 const TAIL_LENGTH: usize = 12;
 const STRIPE_DEPTH: f32 = 1.0 / 64.0;
 const TAIL_Z: f32 = 0.15;
+const TAIL_DUCK_DROOP: f32 = 0.03;  // per segment
 
 pub fn spawn_body (
     hip_entity: Entity,
@@ -333,14 +336,26 @@ pub fn spawn_body (
 
 pub fn animate_tail(
     mut query: Query<(&mut Transform, &TailSegment)>,
+    mut head_query: Query<(&mut Transform, &Head), Without<TailSegment>>,
     player_query: Query<&Player>,
-    animation_state: Res<AnimationState>,
     time: Res<Time>,
-    speed: Res<LevelSpeed>
+    speed: Res<LevelSpeed>,
+    config: Res<DinoRunConfig>
 ) {
-    if animation_state.jumping {
-        let v = player_query.single().unwrap().velocity;
-        let a = (v / JUMP_V) * FRAC_PI_2;
+    let Ok(player) = player_query.single() else {
+        return;
+    };
+    let head_drop = if player.ducking {Vec3::new(0.1, 0.0, -DUCK_HEAD_DROP)} else {Vec3::ZERO};
+    for (mut transform, head) in &mut head_query {
+        let rest = match head.i {
+            0 => Vec3::new(NECK_X, 0.0, 0.1),
+            _ => Vec3::new(SKULL_X, 0.0, 0.15)
+        };
+        transform.translation = rest + head_drop;
+    };
+    if player.airborne {
+        let v = player.velocity;
+        let a = (v / config.jump_v) * FRAC_PI_2;
         let a_step = a / TAIL_LENGTH as f32;
         let wag = 0.5;
         for (mut transform, segment) in &mut query {
@@ -355,7 +370,12 @@ pub fn animate_tail(
         for (mut transform, segment) in &mut query {
             let y = (a + a_step).sin() * (segment.i as f32 * 0.05);
             transform.translation.y = y * (1.0 + segment.i as f32 * 0.05);
-            transform.translation.z = TAIL_Z;
+            // ducking, the tail trails low along the ground
+            transform.translation.z = if player.ducking {
+                TAIL_Z - segment.i as f32 * TAIL_DUCK_DROOP
+            } else {
+                TAIL_Z
+            };
         };
     };
 }
@@ -0,0 +1,342 @@
+use bevy::prelude::*;
+use bevy::color::palettes::css::{LIME, ORANGE_RED};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_3};
+use crate::common::has_arg;
+use crate::dino_run::mechanics::{DUCK_HEAD_DROP, DUCK_HIP_DROP, Obstacle, ObstaclePart, Player};
+
+// shapes sit in their entity's local frame, capsules and prisms run along local z
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub enum Collider {
+    Cuboid {half_size: Vec3},
+    Capsule {half_length: f32, radius: f32},  // half_length is the core segment, without the caps
+    HexPrism {radius: f32, half_height: f32}  // corners like RegularPolygon's, the first on +y
+} impl Collider {
+
+    // radius of a sphere around the local origin that holds the whole shape, for the broad phase
+    pub fn bounding_radius(&self) -> f32 {
+        match *self {
+            Collider::Cuboid {half_size} => half_size.length(),
+            Collider::Capsule {half_length, radius} => half_length + radius,
+            Collider::HexPrism {radius, half_height} => Vec2::new(radius, half_height).length()
+        }
+    }
+
+    // how far the shape reaches from its local origin along the unit vector `d`
+    pub fn extent(&self, d: Vec3) -> f32 {
+        self.local_support(d).dot(d)
+    }
+
+    // the point of the shape furthest along `d`, both in local space
+    fn local_support(&self, d: Vec3) -> Vec3 {
+        let sign = |x: f32| if x >= 0.0 {1.0} else {-1.0};
+        match *self {
+            Collider::Cuboid {half_size} => {
+                Vec3::new(sign(d.x), sign(d.y), sign(d.z)) * half_size
+            },
+            Collider::Capsule {half_length, radius} => {
+                Vec3::Z * half_length * sign(d.z) + d.normalize_or_zero() * radius
+            },
+            Collider::HexPrism {radius, half_height} => {
+                let corner = hex_corners(radius).into_iter()
+                    .max_by(|a, b| a.dot(d.truncate()).total_cmp(&b.dot(d.truncate())))
+                    .unwrap();
+                corner.extend(half_height * sign(d.z))
+            }
+        }
+    }
+
+    fn support(&self, pose: Isometry3d, d: Vec3) -> Vec3 {
+        let local = self.local_support(pose.rotation.inverse() * d);
+        Vec3::from(pose.translation) + pose.rotation * local
+    }
+}
+
+pub fn hex_corners(radius: f32) -> [Vec2; 6] {
+    std::array::from_fn(|i| Vec2::from_angle(FRAC_PI_2 + i as f32 * FRAC_PI_3) * radius)
+}
+
+const GJK_ITERATIONS: usize = 32;
+
+// narrow phase, GJK on the Minkowski difference: the shapes overlap when it holds the origin
+pub fn intersects(a: &Collider, a_pose: Isometry3d, b: &Collider, b_pose: Isometry3d) -> bool {
+    let support = |d: Vec3| a.support(a_pose, d) - b.support(b_pose, -d);
+    let mut d = Vec3::from(b_pose.translation - a_pose.translation);
+    if d.length_squared() < f32::EPSILON {
+        d = Vec3::X;
+    };
+    let mut simplex = vec![support(d)];
+    d = -simplex[0];
+    for _ in 0..GJK_ITERATIONS {
+        if d.length_squared() < f32::EPSILON {
+            // the origin is on the simplex, so the shapes are touching
+            return true;
+        };
+        let p = support(d);
+        if p.dot(d) < 0.0 {
+            return false;
+        };
+        simplex.push(p);
+        if next_simplex(&mut simplex, &mut d) {
+            return true;
+        };
+    };
+    false
+}
+
+// the newest point is always last, returns true once the simplex encloses the origin
+fn next_simplex(simplex: &mut Vec<Vec3>, d: &mut Vec3) -> bool {
+    match simplex.len() {
+        2 => line_case(simplex, d),
+        3 => triangle_case(simplex, d),
+        _ => tetrahedron_case(simplex, d)
+    }
+}
+
+fn line_case(simplex: &mut Vec<Vec3>, d: &mut Vec3) -> bool {
+    let (b, a) = (simplex[0], simplex[1]);
+    let ab = b - a;
+    let ao = -a;
+    if ab.dot(ao) > 0.0 {
+        *d = ab.cross(ao).cross(ab);
+    } else {
+        *simplex = vec![a];
+        *d = ao;
+    };
+    false
+}
+
+fn triangle_case(simplex: &mut Vec<Vec3>, d: &mut Vec3) -> bool {
+    let (c, b, a) = (simplex[0], simplex[1], simplex[2]);
+    let ab = b - a;
+    let ac = c - a;
+    let ao = -a;
+    let abc = ab.cross(ac);
+    if abc.cross(ac).dot(ao) > 0.0 {
+        if ac.dot(ao) > 0.0 {
+            *simplex = vec![c, a];
+            *d = ac.cross(ao).cross(ac);
+            false
+        } else {
+            *simplex = vec![b, a];
+            line_case(simplex, d)
+        }
+    } else if ab.cross(abc).dot(ao) > 0.0 {
+        *simplex = vec![b, a];
+        line_case(simplex, d)
+    } else {
+        *d = if abc.dot(ao) > 0.0 {abc} else {-abc};
+        false
+    }
+}
+
+fn tetrahedron_case(simplex: &mut Vec<Vec3>, d: &mut Vec3) -> bool {
+    let (p, c, b, a) = (simplex[0], simplex[1], simplex[2], simplex[3]);
+    let ao = -a;
+    // each face through the newest point, with the point it doesn't touch
+    for (f1, f2, other) in [(b, c, p), (c, p, b), (p, b, c)] {
+        let mut normal = (f1 - a).cross(f2 - a);
+        if normal.dot(other - a) > 0.0 {
+            normal = -normal;
+        };
+        if normal.dot(ao) > 0.0 {
+            *simplex = vec![f2, f1, a];
+            return triangle_case(simplex, d);
+        };
+    };
+    true
+}
+
+// stand-ins for the dino's LegPart, BodyPart, TailSegment and Head meshes, fixed in a standing pose
+// so the simulation doesn't depend on the walk animation
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HitboxPart {
+    Leg,
+    Body,
+    Tail,
+    Head
+}
+
+// a part's collider and offset in both poses, pose_hitbox copies the right one onto the entity
+#[derive(Component)]
+pub struct Hitbox {
+    pub part: HitboxPart,
+    pub standing: (Collider, Transform),
+    pub ducking: (Collider, Transform)
+}
+
+const HIP: Vec3 = Vec3::new(0.2, 0.0, 0.95);
+const HIP_SPLAY: f32 = 0.25;
+const LEG_RADIUS: f32 = 0.09;
+const TAIL_STEP: f32 = 0.25;
+// only the thick end of the tail collides, nothing taller than a crystal could be cleared under
+// all twelve segments
+const TAIL_HITBOX_SEGMENTS: usize = 4;
+
+// capsule from `a` to `b`, with the transform that places it
+fn capsule_between(a: Vec3, b: Vec3, radius: f32) -> (Collider, Transform) {
+    let span = b - a;
+    let collider = Collider::Capsule {half_length: span.length() / 2.0, radius};
+    let transform = Transform::from_translation((a + b) / 2.0)
+        .with_rotation(Quat::from_rotation_arc(Vec3::Z, span.normalize()));
+    (collider, transform)
+}
+
+fn cuboid_at(center: Vec3, size: Vec3) -> (Collider, Transform) {
+    (Collider::Cuboid {half_size: size / 2.0}, Transform::from_translation(center))
+}
+
+// mirrors the offsets in character.rs, ducking drops the hip with the knees bent forward and
+// tucks the head down on top of that
+fn hitbox_layout(ducking: bool) -> Vec<(HitboxPart, Collider, Transform)> {
+    let mut parts = Vec::new();
+    let (hip, knee, head_drop) = if ducking {
+        (HIP - Vec3::Z * DUCK_HIP_DROP, Vec3::new(0.5, 0.0, 0.3), Vec3::new(0.1, 0.0, -DUCK_HEAD_DROP))
+    } else {
+        (HIP, Vec3::new(0.35, 0.0, 0.5), Vec3::ZERO)
+    };
+    for y in [-HIP_SPLAY, HIP_SPLAY] {
+        let hip = hip + Vec3::Y * y;
+        let knee = knee + Vec3::Y * y;
+        let foot = Vec3::new(0.2, y, LEG_RADIUS);
+        let (c, t) = capsule_between(hip, knee, LEG_RADIUS);
+        parts.push((HitboxPart::Leg, c, t));
+        let (c, t) = capsule_between(knee, foot, LEG_RADIUS);
+        parts.push((HitboxPart::Leg, c, t));
+    };
+    let body_0 = hip + Vec3::new(0.0, 0.0, 0.1);
+    let body_1 = hip + Vec3::new(0.5, 0.0, 0.15);
+    let body_2 = hip + Vec3::new(-0.45, 0.0, 0.2);
+    for (center, size) in [
+        (body_0, Vec3::new(0.5, 0.5, 0.6)),
+        (body_1, Vec3::new(0.5, 0.4, 0.5)),
+        (body_2, Vec3::splat(0.4))
+    ] {
+        let (c, t) = cuboid_at(center, size);
+        parts.push((HitboxPart::Body, c, t));
+    };
+    for i in 0..TAIL_HITBOX_SEGMENTS {
+        let s = 1.0 - (i as f32 * 0.05);
+        let center = body_0 + Vec3::new(-TAIL_STEP * i as f32 - 0.75, 0.0, 0.15);
+        let (c, t) = cuboid_at(center, Vec3::new(TAIL_STEP, 0.3 * s, 0.3 * s));
+        parts.push((HitboxPart::Tail, c, t));
+    };
+    let skull = body_1 + head_drop + Vec3::new(0.65, 0.0, 0.15);
+    for (center, size) in [
+        (body_1 + head_drop + Vec3::new(0.35, 0.0, 0.1), Vec3::new(0.4, 0.4, 0.3)),
+        (skull, Vec3::splat(0.4)),
+        (skull + Vec3::new(0.3, 0.0, -0.1), Vec3::new(0.5, 0.4, 0.2))
+    ] {
+        let (c, t) = cuboid_at(center, size);
+        parts.push((HitboxPart::Head, c, t));
+    };
+    parts
+}
+
+pub fn spawn_hitbox(
+    player: Entity,
+    commands: &mut Commands
+) {
+    let standing = hitbox_layout(false);
+    let ducking = hitbox_layout(true);
+    for ((part, collider, transform), (_, duck_collider, duck_transform)) in standing.into_iter().zip(ducking) {
+        commands.spawn(
+            (
+                Hitbox {part, standing: (collider, transform), ducking: (duck_collider, duck_transform)},
+                collider,
+                transform,
+                ChildOf(player)
+            )
+        );
+    };
+}
+
+pub fn pose_hitbox(
+    player_query: Query<&Player>,
+    mut hitbox_query: Query<(&Hitbox, &mut Collider, &mut Transform)>
+) {
+    let Ok(player) = player_query.single() else {
+        return;
+    };
+    for (hitbox, mut collider, mut transform) in &mut hitbox_query {
+        let (c, t) = if player.ducking {hitbox.ducking} else {hitbox.standing};
+        collider.set_if_neq(c);
+        transform.set_if_neq(t);
+    };
+}
+
+// the player never moves along x or y, so a hitbox's pose is its offset lifted by the jump
+pub fn hitbox_pose(player_z: f32, local: &Transform) -> Isometry3d {
+    Isometry3d::new(local.translation + Vec3::Z * player_z, local.rotation)
+}
+
+// obstacles sit on the ground at y = 0, so a part's pose is its offset slid along x
+pub fn obstacle_part_pose(obstacle_x: f32, local: &Transform) -> Isometry3d {
+    Isometry3d::new(local.translation + Vec3::X * obstacle_x, local.rotation)
+}
+
+// F3 or `--colliders` draws every collider over the scene
+#[derive(Resource, Default, PartialEq)]
+pub struct ShowColliders(pub bool);
+impl ShowColliders {
+    pub fn from_env() -> Self {
+        Self(has_arg(COLLIDERS_ARG))
+    }
+}
+
+pub const COLLIDERS_ARG: &str = "--colliders";
+pub const COLLIDERS_KEY: KeyCode = KeyCode::F3;
+
+pub fn toggle_collider_gizmos(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut show: ResMut<ShowColliders>
+) {
+    if keys.just_pressed(COLLIDERS_KEY) {
+        show.0 = !show.0;
+    };
+}
+
+fn draw_collider(gizmos: &mut Gizmos, collider: &Collider, pose: Isometry3d, color: Color) {
+    match *collider {
+        Collider::Cuboid {half_size} => {
+            let transform = Transform::from_translation(pose.translation.into())
+                .with_rotation(pose.rotation)
+                .with_scale(half_size * 2.0);
+            gizmos.cuboid(transform, color);
+        },
+        Collider::Capsule {half_length, radius} => {
+            gizmos.primitive_3d(&Capsule3d::new(radius, half_length * 2.0), pose, color);
+        },
+        Collider::HexPrism {radius, half_height} => {
+            let corners = hex_corners(radius);
+            let point = |corner: Vec2, z: f32| pose.transform_point(corner.extend(z)).into();
+            for z in [-half_height, half_height] {
+                gizmos.linestrip(corners.iter().chain(&corners[..1]).map(|&c| point(c, z)), color);
+            };
+            for &corner in &corners {
+                gizmos.line(point(corner, -half_height), point(corner, half_height), color);
+            };
+        }
+    };
+}
+
+// drawn at the interpolated positions, so the boxes line up with the meshes between fixed steps
+pub fn draw_collider_gizmos(
+    mut gizmos: Gizmos,
+    player_query: Query<&Transform, With<Player>>,
+    hitbox_query: Query<(&Collider, &Transform), With<Hitbox>>,
+    part_query: Query<(&Collider, &Transform, &ChildOf), With<ObstaclePart>>,
+    obstacle_query: Query<&Transform, With<Obstacle>>
+) {
+    let Ok(player) = player_query.single() else {
+        return;
+    };
+    for (collider, local) in hitbox_query {
+        draw_collider(&mut gizmos, collider, hitbox_pose(player.translation.z, local), LIME.into());
+    };
+    for (collider, local, child_of) in part_query {
+        let Ok(obstacle) = obstacle_query.get(child_of.parent()) else {
+            continue;
+        };
+        draw_collider(&mut gizmos, collider, obstacle_part_pose(obstacle.translation.x, local), ORANGE_RED.into());
+    };
+}
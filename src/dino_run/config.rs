@@ -0,0 +1,209 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::common::{arg_value, save_ron};
+use crate::dino_run::difficulty::DifficultyCurve;
+use crate::dino_run::mechanics::PlayerHealth;
+use crate::dino_run::state::DinoRunState;
+
+pub const CONFIG_ARG: &str = "--config";
+const CONFIG_FILE: &str = "dino_run_config.ron";
+const CONFIG_POLL_SECS: f32 = 1.0;
+
+// the feel of the game, everything a designer might want to nudge without a rebuild
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]  // so a typo is an error rather than silently ignored
+pub struct DinoRunConfig {
+    pub jump_v: f32,
+    pub gravity: f32,
+    pub hold_gravity_scale: f32,  // gravity while rising with the jump key held
+    pub fast_fall_gravity_scale: f32,  // gravity while ducking in the air
+    pub jump_buffer: f32,  // seconds, a press this long before landing jumps as soon as the dino touches down
+    pub coyote_time: f32,  // seconds, a press this long after the ground has gone from under the dino still jumps
+    pub spawn_x: f32,  // where obstacles and cave crystals appear
+    pub despawn_x: f32,  // and where they're removed, behind the dino
+    pub lead_in: f32,  // extra distance before the first obstacle of a run
+    pub flash_dur: u8,  // fixed steps of invulnerability after a hit
+    pub flick_dur: u8,  // fixed steps per blink while invulnerable
+    pub max_lives: u8,  // takes effect from the next run
+    pub difficulty: DifficultyCurve
+} impl Default for DinoRunConfig {
+    fn default() -> Self {
+        Self {
+            jump_v: 10.0,
+            gravity: 40.0,
+            hold_gravity_scale: 0.5,
+            fast_fall_gravity_scale: 2.5,
+            jump_buffer: 0.1,
+            coyote_time: 0.08,
+            spawn_x: 15.0,
+            despawn_x: -7.0,
+            lead_in: 5.0,
+            flash_dur: 40,
+            flick_dur: 4,
+            max_lives: 3,
+            difficulty: DifficultyCurve::default()
+        }
+    }
+} impl DinoRunConfig {
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("art_fight").join(CONFIG_FILE))
+    }
+
+    // time from take-off to landing for a jump held all the way to the apex, the longest one there is
+    pub fn jump_airtime(&self) -> f32 {
+        let rise_g = self.gravity * self.hold_gravity_scale;
+        let rise = self.jump_v / rise_g;
+        let apex = self.jump_v * self.jump_v / (2.0 * rise_g);
+        let fall = (2.0 * apex / self.gravity).sqrt();
+        rise + fall
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("jump_v", self.jump_v),
+            ("gravity", self.gravity),
+            ("hold_gravity_scale", self.hold_gravity_scale),
+            ("fast_fall_gravity_scale", self.fast_fall_gravity_scale),
+            ("difficulty.ramp", self.difficulty.ramp),
+            ("difficulty.start_speed", self.difficulty.start_speed),
+            ("difficulty.max_speed", self.difficulty.max_speed),
+            ("difficulty.start_gap", self.difficulty.start_gap),
+            ("difficulty.end_gap", self.difficulty.end_gap)
+        ];
+        for (name, value) in positive {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(format!("{} must be above 0, not {}", name, value));
+            };
+        };
+        let non_negative = [
+            ("jump_buffer", self.jump_buffer),
+            ("coyote_time", self.coyote_time),
+            ("lead_in", self.lead_in),
+            ("difficulty.jitter", self.difficulty.jitter)
+        ];
+        for (name, value) in non_negative {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("{} can't be below 0, not {}", name, value));
+            };
+        };
+        if self.difficulty.max_speed < self.difficulty.start_speed {
+            return Err(format!(
+                "difficulty.max_speed ({}) can't be below difficulty.start_speed ({})",
+                self.difficulty.max_speed, self.difficulty.start_speed
+            ));
+        };
+        if self.difficulty.jitter >= 1.0 {
+            return Err(format!("difficulty.jitter must be below 1, not {}", self.difficulty.jitter));
+        };
+        if !self.spawn_x.is_finite() || !self.despawn_x.is_finite() || self.spawn_x <= self.despawn_x {
+            return Err(format!("spawn_x ({}) must be ahead of despawn_x ({})", self.spawn_x, self.despawn_x));
+        };
+        if self.flick_dur == 0 {
+            return Err("flick_dur must be at least 1".to_string());
+        };
+        if self.max_lives == 0 {
+            return Err("max_lives must be at least 1".to_string());
+        };
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: Self = ron::from_str(text).map_err(|e| format!("unreadable ({})", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        save_ron(path, self)
+    }
+}
+
+// where the config came from, watched for edits while the game runs
+#[derive(Resource)]
+pub struct ConfigFile {
+    pub path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    timer: Timer
+} impl ConfigFile {
+
+    // `--config my_config.ron` picks another file
+    pub fn from_env() -> Self {
+        let path = arg_value(CONFIG_ARG).map(PathBuf::from).or_else(DinoRunConfig::default_path);
+        Self {path, modified: None, timer: Timer::from_seconds(CONFIG_POLL_SECS, TimerMode::Repeating)}
+    }
+
+    fn modified_time(&self) -> Option<SystemTime> {
+        self.path.as_ref().and_then(|path| fs::metadata(path).ok()?.modified().ok())
+    }
+
+    // a missing file is written out with the defaults so there's something to edit, a broken one is
+    // left alone for whoever's editing it
+    pub fn load(&mut self) -> DinoRunConfig {
+        let Some(path) = self.path.clone() else {
+            eprintln!("No config directory found, using the default tuning");
+            return DinoRunConfig::default();
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => {
+                let config = DinoRunConfig::default();
+                match config.save(&path) {
+                    Ok(()) => println!("Wrote the default tuning to {}", path.display()),
+                    Err(e) => eprintln!("Couldn't write the default tuning: {}", e)
+                };
+                self.modified = self.modified_time();
+                return config;
+            }
+        };
+        self.modified = self.modified_time();
+        DinoRunConfig::parse(&text).unwrap_or_else(|reason| {
+            eprintln!("Ignoring tuning at {}: {}, using the defaults", path.display(), reason);
+            DinoRunConfig::default()
+        })
+    }
+}
+
+// a new limit waits for the next run, unless no run has started yet
+pub fn apply_max_lives(
+    config: Res<DinoRunConfig>,
+    state: Res<State<DinoRunState>>,
+    mut health: ResMut<PlayerHealth>
+) {
+    health.max_lives = config.max_lives;
+    if *state.get() == DinoRunState::Title {
+        health.reset();
+    };
+}
+
+// checks the file's timestamp now and then, and swaps the new values in if they're valid
+pub fn reload_config(
+    mut config_file: ResMut<ConfigFile>,
+    mut config: ResMut<DinoRunConfig>,
+    time: Res<Time<Real>>
+) {
+    if !config_file.timer.tick(time.delta()).just_finished() {
+        return;
+    };
+    let modified = config_file.modified_time();
+    if modified.is_none() || modified == config_file.modified {
+        return;
+    };
+    config_file.modified = modified;
+    let Some(path) = config_file.path.clone() else {
+        return;
+    };
+    let result = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| DinoRunConfig::parse(&text));
+    match result {
+        Ok(new_config) => {
+            println!("Reloaded tuning from {}", path.display());
+            config.set_if_neq(new_config);
+        },
+        Err(reason) => eprintln!("Keeping the current tuning, {} is bad: {}", path.display(), reason)
+    };
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::dino_run::config::DinoRunConfig;
+use crate::dino_run::mechanics::LevelSpeed;
+use crate::dino_run::obstacles::OBSTACLE_RADIUS;
+use crate::dino_run::stats::RunStats;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DifficultyDriver {
+    Time,  // seconds survived
+    Score  // obstacles cleared
+}
+
+// every value ramps linearly from its start to its end over `ramp` units of the driver, then holds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DifficultyCurve {
+    pub driver: DifficultyDriver,
+    pub ramp: f32,
+    pub start_speed: f32,
+    pub max_speed: f32,
+    pub start_gap: f32,  // world units between obstacles
+    pub end_gap: f32,
+    pub jitter: f32  // +/- fraction of the gap
+} impl Default for DifficultyCurve {
+    fn default() -> Self {
+        Self {
+            driver: DifficultyDriver::Time,
+            ramp: 120.0,
+            start_speed: 5.0,
+            max_speed: 11.0,
+            start_gap: 10.0,
+            end_gap: 13.0,
+            jitter: 0.5
+        }
+    }
+} impl DifficultyCurve {
+
+    fn progress(&self, stats: &RunStats) -> f32 {
+        let x = match self.driver {
+            DifficultyDriver::Time => stats.elapsed,
+            DifficultyDriver::Score => stats.score() as f32
+        };
+        (x / self.ramp).clamp(0.0, 1.0)
+    }
+
+    pub fn speed(&self, stats: &RunStats) -> f32 {
+        let t = self.progress(stats);
+        self.start_speed + (self.max_speed - self.start_speed) * t
+    }
+
+    // how far `speed` is along the way from start_speed to max_speed, 0 to 1
+    pub fn intensity(&self, speed: f32) -> f32 {
+        let range = self.max_speed - self.start_speed;
+        if range <= 0.0 {
+            return 0.0;
+        };
+        ((speed - self.start_speed) / range).clamp(0.0, 1.0)
+    }
+
+    pub fn gap(&self, stats: &RunStats) -> f32 {
+        let t = self.progress(stats);
+        self.start_gap + (self.end_gap - self.start_gap) * t
+    }
+
+    // `r` is a uniform random number in 0..1, the result is never shorter than the fair minimum for a
+    // jump lasting `airtime`
+    pub fn next_gap(&self, stats: &RunStats, speed: f32, airtime: f32, r: f32) -> f32 {
+        let gap = self.gap(stats);
+        let jittered = gap + gap * self.jitter * (r * 2.0 - 1.0);
+        jittered.max(self.tight_gap(speed, airtime))
+    }
+
+    // the shortest gap that's still fair at this speed
+    pub fn tight_gap(&self, speed: f32, airtime: f32) -> f32 {
+        // the level keeps speeding up while the obstacle scrolls towards the dino
+        let arrival_speed = (speed * FAIR_SPEED_MARGIN).min(self.max_speed.max(speed));
+        min_fair_gap(arrival_speed, airtime)
+    }
+}
+
+const FAIR_SPEED_MARGIN: f32 = 1.1;
+
+// the dino can't jump again until it lands, so two obstacles closer together than the ground
+// covered during a full held jump, plus one obstacle's width, can't both be cleared
+pub fn min_fair_gap(speed: f32, airtime: f32) -> f32 {
+    airtime * speed + OBSTACLE_RADIUS * 2.0
+}
+
+pub fn apply_difficulty(
+    config: Res<DinoRunConfig>,
+    stats: Res<RunStats>,
+    mut speed: ResMut<LevelSpeed>
+) {
+    speed.f32 = config.difficulty.speed(&stats);
+}
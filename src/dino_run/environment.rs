@@ -9,6 +9,8 @@ use crate::dino_run::character::PITCH_CONSTANT;
 use fastrand::Rng;
 use crate::common::MaterialWizard;
 use crate::dino_run::mechanics::LevelSpeed;
+use crate::dino_run::config::DinoRunConfig;
+use crate::dino_run::seed::RunSeed;
 
 const CAVE_RADIUS: f32 = 3.0;
 const CAVE_LENGTH: f32 = 50.0;
@@ -88,12 +90,13 @@ pub fn spawn_cave_tunnel(
 pub fn insert_crystal_stuff(
     mut commands: Commands,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    run_seed: Res<RunSeed>
 ) {
     let hues: Vec<f32> = {
         let normals = MaterialWizard::generate_normal_hue_vec(32);
         let mut hues: Vec<f32> = Vec::with_capacity(32);
-        for i in 0..32 {
-            hues.push(normals[i] * 360.0);
+        for normal in normals.iter().take(32) {
+            hues.push(normal * 360.0);
         };
         hues
     };
@@ -114,7 +117,7 @@ pub fn insert_crystal_stuff(
     );
 
     commands.insert_resource(CrystalAssets{wizard, hues});
-    commands.insert_resource(CrystalTimer::new());
+    commands.insert_resource(CrystalTimer::new(run_seed.crystal_rng()));
     commands.insert_resource(AmbientLight {
         color: Color::WHITE,
         brightness: 80.0,
@@ -126,8 +129,8 @@ pub struct CrystalTimer{
     rng: Rng,
     last_x: f32
 } impl CrystalTimer {
-    fn new() -> Self {
-        Self {rng: Rng::new(), last_x: 0.0}
+    fn new(rng: Rng) -> Self {
+        Self {rng, last_x: 0.0}
     }
 }
 
@@ -144,10 +147,11 @@ pub fn spawn_crystals(  // this shooouuuuld be event based, but I can't be bothe
     mut crystal_timer: ResMut<CrystalTimer>,
     crystal_assets: Res<CrystalAssets>,
     time: Res<Time>,
-    speed: Res<LevelSpeed>
+    speed: Res<LevelSpeed>,
+    config: Res<DinoRunConfig>
 ) {
     crystal_timer.last_x -= time.delta_secs() * speed.f32;
-    while crystal_timer.last_x <= 15.0 {
+    while crystal_timer.last_x <= config.spawn_x {
         let randoms = [crystal_timer.rng.f32(); 5];
         crystal_timer.last_x += randoms[0] * 0.5 + 1.5;
         let crystal_length = 0.5 + (randoms[2] - 0.5) * 0.2;
@@ -192,14 +196,27 @@ pub fn update_lights(
     mut query: Query<(&mut Transform, Entity), With<CrystalLight>>,
     time: Res<Time>,
     mut commands: Commands,
-    speed: Res<LevelSpeed>
+    speed: Res<LevelSpeed>,
+    config: Res<DinoRunConfig>
 ) {
     let dt = time.delta_secs();
     let step = speed.f32 * dt;
     for (mut t, e) in &mut query {
         t.translation.x -= step;
-        if t.translation.x < -7.0 {
+        if t.translation.x < config.despawn_x {
             commands.entity(e).despawn()
         };
     };
 }
+
+pub fn reset_crystals(
+    query: Query<Entity, With<CrystalLight>>,
+    mut commands: Commands,
+    mut crystal_timer: ResMut<CrystalTimer>,
+    run_seed: Res<RunSeed>
+) {
+    for e in query {
+        commands.entity(e).despawn()
+    };
+    *crystal_timer = CrystalTimer::new(run_seed.crystal_rng());
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use bevy::input::InputPlugin;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use fastrand::Rng;
+use crate::dino_run::mechanics::{
+    DinoRunSimPlugin, JumpInput, LevelSpeed, Obstacle, PHYSICS_HZ, Player, player_jump_system
+};
+use crate::dino_run::obstacles::ObstacleCatalogue;
+use crate::dino_run::replay::record_input;
+use crate::dino_run::seed::RunSeed;
+use crate::dino_run::state::DinoRunState;
+use crate::dino_run::stats::RunStats;
+
+pub const HEADLESS_ARG: &str = "--headless";
+const MAX_RUN_SECS: f64 = 600.0;
+const AUTOPILOT_LEAD: f32 = 0.45;  // seconds before an obstacle reaches the dino that the autopilot jumps
+const AUTOPILOT_MISS_CHANCE: f32 = 0.1;
+
+// no window, renderer or audio, and every update() advances exactly one fixed step
+pub fn headless_app(run_seed: RunSeed) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, InputPlugin));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / PHYSICS_HZ)));
+    app.insert_resource(run_seed);
+    app.add_plugins(DinoRunSimPlugin);
+    app.finish();
+    app.cleanup();
+    app
+}
+
+// a simple bot for unattended runs, it jumps for each obstacle unless it rolls a miss
+#[derive(Resource)]
+pub struct Autopilot {
+    rng: Rng,
+    miss_chance: f32,
+    decided: Option<(u32, bool)>  // obstacle count and whether it's jumping for it
+} impl Autopilot {
+    pub fn new(seed: u64, miss_chance: f32) -> Self {
+        Self {rng: Rng::with_seed(seed), miss_chance, decided: None}
+    }
+}
+
+pub fn autopilot(
+    mut pilot: ResMut<Autopilot>,
+    mut jump_input: ResMut<JumpInput>,
+    player_query: Query<&Player>,
+    obstacle_query: Query<&Obstacle>,
+    speed: Res<LevelSpeed>,
+    catalogue: Res<ObstacleCatalogue>
+) {
+    let Ok(player) = player_query.single() else {
+        return;
+    };
+    jump_input.held = player.airborne && player.velocity > 0.0;
+    // stay down until anything overhead has gone by
+    jump_input.duck = obstacle_query.iter().any(|o| {
+        !o.scored && catalogue.kinds[o.kind].overhead() && o.x < speed.f32 * AUTOPILOT_LEAD
+    });
+    if player.airborne {
+        return;
+    };
+    let next = obstacle_query.iter()
+        .filter(|o| !o.scored && o.x > 0.0)
+        .min_by(|a, b| a.x.total_cmp(&b.x));
+    let Some(obstacle) = next else {
+        return;
+    };
+    if obstacle.x > speed.f32 * AUTOPILOT_LEAD || catalogue.kinds[obstacle.kind].overhead() {
+        return;
+    };
+    let jumping = match pilot.decided {
+        Some((count, jumping)) if count == obstacle.count => jumping,
+        _ => {
+            let jumping = pilot.rng.f32() >= pilot.miss_chance;
+            pilot.decided = Some((obstacle.count, jumping));
+            jumping
+        }
+    };
+    if jumping {
+        jump_input.pressed = true;
+        jump_input.held = true;
+    };
+}
+
+// a headless app with the autopilot at the controls, the run starts on the first update
+pub fn autopilot_app(run_seed: RunSeed, miss_chance: f32) -> App {
+    let mut app = headless_app(run_seed);
+    app.insert_resource(Autopilot::new(run_seed.seed, miss_chance));
+    app.add_systems(FixedUpdate, autopilot
+        .before(record_input)
+        .before(player_jump_system)
+        .run_if(in_state(DinoRunState::Running)));
+    app.world_mut().resource_mut::<NextState<DinoRunState>>().set(DinoRunState::Running);
+    app
+}
+
+// updates until game over or MAX_RUN_SECS, whichever comes first
+pub fn run_to_game_over(app: &mut App) -> RunStats {
+    let max_steps = (MAX_RUN_SECS * PHYSICS_HZ) as u32;
+    for _ in 0..max_steps {
+        app.update();
+        if *app.world().resource::<State<DinoRunState>>().get() == DinoRunState::GameOver {
+            break;
+        };
+    };
+    app.world().resource::<RunStats>().clone()
+}
+
+pub fn simulate_run(run_seed: RunSeed, miss_chance: f32) -> RunStats {
+    run_to_game_over(&mut autopilot_app(run_seed, miss_chance))
+}
+
+// `--headless 1000` plays that many autopilot runs and prints how they went
+pub fn run_headless(runs: u32, run_seed: RunSeed) {
+    let mut total_score = 0u64;
+    let mut best: Option<(u64, RunStats)> = None;
+    for i in 0..runs {
+        let seed = if run_seed.fixed {
+            run_seed.seed.wrapping_add(i as u64)
+        } else {
+            fastrand::u64(..)
+        };
+        let stats = simulate_run(RunSeed::fixed(seed), AUTOPILOT_MISS_CHANCE);
+        println!(
+            "run {}: seed {} score {} best streak {} hits {} {:.0}m in {:.1}s",
+            i, seed, stats.score(), stats.best_streak, stats.hits, stats.distance, stats.elapsed
+        );
+        total_score += stats.score() as u64;
+        if best.as_ref().is_none_or(|(_, b)| stats.score() > b.score()) {
+            best = Some((seed, stats));
+        };
+    };
+    if let Some((seed, stats)) = best {
+        println!(
+            "{} runs, mean score {:.1}, best {} (seed {})",
+            runs, total_score as f64 / runs as f64, stats.score(), seed
+        );
+    };
+}
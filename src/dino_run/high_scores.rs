@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::common::{load_versioned, save_ron};
+use crate::dino_run::seed::RunSeed;
+use crate::dino_run::stats::RunStats;
+
+const HIGH_SCORE_VERSION: u32 = 1;
+const HIGH_SCORE_COUNT: usize = 10;
+const HIGH_SCORE_FILE: &str = "dino_run_scores.ron";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HighScore {
+    pub score: u32,
+    pub distance: f32,
+    pub best_streak: u32,
+    pub date: u64,  // seconds since the unix epoch
+    pub seed: Option<u64>
+} impl HighScore {
+
+    pub fn from_stats(stats: &RunStats, seed: Option<u64>) -> Self {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            score: stats.score(),
+            distance: stats.distance,
+            best_streak: stats.best_streak,
+            date,
+            seed
+        }
+    }
+
+    fn beats(&self, other: &HighScore) -> bool {
+        (self.score, self.distance) > (other.score, other.distance)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct HighScoreFile {
+    version: u32,
+    scores: Vec<HighScore>
+}
+
+#[derive(Resource)]
+pub struct HighScores {
+    pub scores: Vec<HighScore>,
+    pub capacity: usize,
+    path: Option<PathBuf>
+} impl HighScores {
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("art_fight").join(HIGH_SCORE_FILE))
+    }
+
+    pub fn load(path: Option<PathBuf>, capacity: usize) -> Self {
+        let mut high_scores = Self {scores: Vec::new(), capacity, path};
+        let Some(path) = &high_scores.path else {
+            eprintln!("No data directory found, high scores won't be saved");
+            return high_scores;
+        };
+        if let Ok(Some(file)) = load_versioned::<HighScoreFile>(path, HIGH_SCORE_VERSION, "high scores") {
+            let mut scores = file.scores;
+            scores.sort_by(|a, b| b.score.cmp(&a.score).then(b.distance.total_cmp(&a.distance)));
+            scores.truncate(capacity);
+            high_scores.scores = scores;
+        };
+        high_scores
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        save_ron(path, &HighScoreFile {version: HIGH_SCORE_VERSION, scores: self.scores.clone()})
+    }
+
+    // returns the 0-based rank the entry landed at, or None if it didn't make the table.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let rank = self.scores.iter().position(|s| entry.beats(s)).unwrap_or(self.scores.len());
+        if rank >= self.capacity {
+            return None;
+        };
+        self.scores.insert(rank, entry);
+        self.scores.truncate(self.capacity);
+        Some(rank)
+    }
+}
+
+pub fn load_high_scores(
+    mut commands: Commands
+) {
+    commands.insert_resource(HighScores::load(HighScores::default_path(), HIGH_SCORE_COUNT));
+}
+
+pub fn record_high_score(
+    stats: Res<RunStats>,
+    run_seed: Res<RunSeed>,
+    mut high_scores: ResMut<HighScores>
+) {
+    let entry = HighScore::from_stats(&stats, Some(run_seed.seed));
+    if let Some(rank) = high_scores.insert(entry) {
+        println!("New high score! #{}", rank + 1);
+        if let Err(e) = high_scores.save() {
+            eprintln!("Couldn't save high scores: {}", e);
+        };
+    };
+}
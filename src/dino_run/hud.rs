@@ -0,0 +1,231 @@
+use bevy::prelude::*;
+use crate::dino_run::mechanics::{LevelSpeed, Player, PlayerHealth, PlayerHurt, PlayerScores};
+use crate::dino_run::seed::RunSeed;
+use crate::dino_run::state::DinoRunState;
+use crate::dino_run::stats::RunStats;
+
+const HUD_FONT_SIZE: f32 = 24.0;
+const BANNER_FONT_SIZE: f32 = 40.0;
+const POPUP_FONT_SIZE: f32 = 32.0;
+const POPUP_DUR: f32 = 0.8;
+const POPUP_RISE: f32 = 80.0;  // pixels over the popup's lifetime
+const VIGNETTE_DUR: f32 = 0.6;
+const VIGNETTE_ALPHA: f32 = 0.6;
+const VIGNETTE_WIDTH: f32 = 64.0;
+
+#[derive(Component, Copy, Clone, PartialEq, Eq)]
+pub enum HudText {
+    Score,
+    Streak,
+    Lives,
+    Speed
+} impl HudText {
+    const ALL: [HudText; 4] = [
+        Self::Score,
+        Self::Streak,
+        Self::Lives,
+        Self::Speed,
+    ];
+}
+
+#[derive(Component)]
+pub struct StateBanner;
+
+#[derive(Component)]
+pub struct HurtVignette {
+    remaining: f32
+}
+
+#[derive(Component)]
+pub struct ScorePopup {
+    age: f32,
+    top: f32
+}
+
+pub fn spawn_hud(
+    mut commands: Commands
+) {
+    let stat_column = commands.spawn(
+        (
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(12.0),
+                left: Val::Px(16.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+        )
+    ).id();
+    for hud_text in HudText::ALL {
+        commands.spawn(
+            (
+                Text::default(),
+                TextFont {
+                    font_size: HUD_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                hud_text,
+                ChildOf(stat_column)
+            )
+        );
+    };
+    let banner_row = commands.spawn(
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            top: Val::Percent(20.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        }
+    ).id();
+    commands.spawn(
+        (
+            Text::default(),
+            TextFont {
+                font_size: BANNER_FONT_SIZE,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            TextLayout::new_with_justify(JustifyText::Center),
+            StateBanner,
+            ChildOf(banner_row)
+        )
+    );
+    commands.spawn(
+        (
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                border: UiRect::all(Val::Px(VIGNETTE_WIDTH)),
+                ..default()
+            },
+            BorderColor(Color::NONE),
+            BackgroundColor(Color::NONE),
+            HurtVignette {remaining: 0.0}
+        )
+    );
+}
+
+pub fn update_hud(
+    mut query: Query<(&mut Text, &HudText)>,
+    stats: Res<RunStats>,
+    health: Res<PlayerHealth>,
+    speed: Res<LevelSpeed>
+) {
+    for (mut text, hud_text) in &mut query {
+        let new_text = match hud_text {
+            HudText::Score => format!("Score: {}", stats.score()),
+            HudText::Streak => format!("Streak: {} (best {})", stats.streak, stats.best_streak),
+            HudText::Lives => format!("Lives: {}/{}", health.lives, health.max_lives),
+            HudText::Speed => format!("Speed: {:.1}", speed.f32)
+        };
+        // only touch the text when it changes, so it isn't re-laid out every frame
+        if **text != new_text {
+            **text = new_text;
+        };
+    };
+}
+
+pub fn update_banner(
+    mut query: Query<&mut Text, With<StateBanner>>,
+    state: Res<State<DinoRunState>>,
+    stats: Res<RunStats>,
+    run_seed: Res<RunSeed>
+) {
+    let Ok(mut text) = query.single_mut() else {
+        return;
+    };
+    **text = match state.get() {
+        DinoRunState::Title => "Chompless and The Crystal Cavern\nJump to start".to_string(),
+        DinoRunState::Running => String::new(),
+        DinoRunState::Paused => "Paused".to_string(),
+        DinoRunState::GameOver => format!(
+            "Game over!\nScore {}  Best streak {}  {:.0}m\nSeed {}\nJump to try again",
+            stats.score(), stats.best_streak, stats.distance, run_seed.seed
+        )
+    };
+}
+
+pub fn spawn_score_popups(
+    mut commands: Commands,
+    mut event_reader: EventReader<PlayerScores>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    player_query: Query<&GlobalTransform, With<Player>>
+) {
+    let (Ok((camera, camera_transform)), Ok(player_transform)) = (camera_query.single(), player_query.single()) else {
+        return;
+    };
+    let head = player_transform.translation() + Vec3::new(0.0, 0.0, 2.5);
+    let Ok(screen_pos) = camera.world_to_viewport(camera_transform, head) else {
+        return;
+    };
+    for _ in event_reader.read() {
+        commands.spawn(
+            (
+                Text::new("+1"),
+                TextFont {
+                    font_size: POPUP_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::hsl(58.0, 1.0, 0.5)),
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(screen_pos.x),
+                    top: Val::Px(screen_pos.y),
+                    ..default()
+                },
+                ScorePopup {age: 0.0, top: screen_pos.y}
+            )
+        );
+    };
+}
+
+pub fn animate_score_popups(
+    mut commands: Commands,
+    mut query: Query<(&mut ScorePopup, &mut Node, &mut TextColor, Entity)>,
+    time: Res<Time>
+) {
+    let dt = time.delta_secs();
+    for (mut popup, mut node, mut color, entity) in &mut query {
+        popup.age += dt;
+        if popup.age >= POPUP_DUR {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let t = popup.age / POPUP_DUR;
+        node.top = Val::Px(popup.top - t * POPUP_RISE);
+        color.0.set_alpha(1.0 - t * t);
+    };
+}
+
+pub fn hurt_vignette(
+    mut query: Query<(&mut HurtVignette, &mut BorderColor, &mut BackgroundColor)>,
+    mut event_reader: EventReader<PlayerHurt>,
+    time: Res<Time>
+) {
+    let Ok((mut vignette, mut border, mut background)) = query.single_mut() else {
+        return;
+    };
+    for _ in event_reader.read() {
+        vignette.remaining = VIGNETTE_DUR;
+    };
+    if vignette.remaining <= 0.0 {
+        return;
+    };
+    vignette.remaining = (vignette.remaining - time.delta_secs()).max(0.0);
+    let pulse = vignette.remaining / VIGNETTE_DUR;
+    border.0 = Color::linear_rgba(1.0, 0.0, 0.0, pulse * VIGNETTE_ALPHA);
+    background.0 = Color::linear_rgba(1.0, 0.0, 0.0, pulse * VIGNETTE_ALPHA * 0.15);
+}
+
+pub fn clear_score_popups(
+    mut commands: Commands,
+    query: Query<Entity, With<ScorePopup>>
+) {
+    for entity in query {
+        commands.entity(entity).despawn();
+    };
+}
@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::common::{load_versioned, save_ron};
+
+const INPUT_BINDINGS_VERSION: u32 = 1;
+const INPUT_BINDINGS_FILE: &str = "dino_run_input.ron";
+
+// what the game listens for, rather than which key or button caused it
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Jump,
+    Duck,
+    Pause,
+    Restart
+} impl Action {
+    pub const ALL: [Action; 4] = [Action::Jump, Action::Duck, Action::Pause, Action::Restart];
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Gamepad(GamepadButton),  // on any connected gamepad
+    Mouse(MouseButton),
+    Touch  // a finger anywhere on the screen
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InputBindings {
+    pub jump: Vec<Binding>,
+    pub duck: Vec<Binding>,
+    pub pause: Vec<Binding>,
+    pub restart: Vec<Binding>
+} impl Default for InputBindings {
+    fn default() -> Self {
+        use Binding::*;
+        Self {
+            jump: vec![
+                Key(KeyCode::Space), Key(KeyCode::ArrowUp), Key(KeyCode::KeyW),
+                Gamepad(GamepadButton::South), Gamepad(GamepadButton::DPadUp),
+                Mouse(MouseButton::Left), Touch
+            ],
+            duck: vec![
+                Key(KeyCode::KeyS), Key(KeyCode::ArrowDown), Key(KeyCode::ControlLeft), Key(KeyCode::ControlRight),
+                Gamepad(GamepadButton::East), Gamepad(GamepadButton::DPadDown),
+                Mouse(MouseButton::Right)
+            ],
+            pause: vec![Key(KeyCode::Escape), Key(KeyCode::KeyP), Gamepad(GamepadButton::Start)],
+            restart: vec![Key(KeyCode::KeyR), Gamepad(GamepadButton::Select)]
+        }
+    }
+} impl InputBindings {
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("art_fight").join(INPUT_BINDINGS_FILE))
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        match action {
+            Action::Jump => &self.jump,
+            Action::Duck => &self.duck,
+            Action::Pause => &self.pause,
+            Action::Restart => &self.restart
+        }
+    }
+
+    // the first run saves the defaults, so players have a file to rebind keys in
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let Some(path) = path else {
+            eprintln!("No config directory found, using the default input bindings");
+            return Self::default();
+        };
+        match load_versioned::<InputBindingsFile>(&path, INPUT_BINDINGS_VERSION, "input bindings") {
+            Ok(Some(file)) => file.bindings,
+            Ok(None) => {
+                let bindings = Self::default();
+                match bindings.save(&path) {
+                    Ok(()) => println!("Wrote the default input bindings to {}", path.display()),
+                    Err(e) => eprintln!("Couldn't write the default input bindings: {}", e)
+                };
+                bindings
+            },
+            Err(_) => Self::default()
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        save_ron(path, &InputBindingsFile {version: INPUT_BINDINGS_VERSION, bindings: self.clone()})
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct InputBindingsFile {
+    version: u32,
+    bindings: InputBindings
+}
+
+pub fn load_input_bindings(
+    mut commands: Commands
+) {
+    commands.insert_resource(InputBindings::load(InputBindings::default_path()));
+}
+
+// folds every bound key, button and touch into one ButtonInput<Action> per frame
+pub fn update_actions(
+    bindings: Res<InputBindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    gamepads: Query<&Gamepad>,
+    mut actions: ResMut<ButtonInput<Action>>
+) {
+    actions.clear();
+    for action in Action::ALL {
+        // a press and release inside one frame still counts as a press
+        let down = bindings.get(action).iter().any(|binding| match *binding {
+            Binding::Key(key) => keys.pressed(key) || keys.just_pressed(key),
+            Binding::Gamepad(button) => gamepads.iter().any(|g| g.pressed(button) || g.just_pressed(button)),
+            Binding::Mouse(button) => mouse.pressed(button) || mouse.just_pressed(button),
+            Binding::Touch => touches.iter().next().is_some() || touches.any_just_pressed()
+        });
+        if down {
+            actions.press(action);
+        } else {
+            actions.release(action);
+        };
+    };
+}
@@ -1,144 +1,266 @@
 use bevy::prelude::*;
-use bevy::pbr::{NotShadowCaster, NotShadowReceiver};
-use bevy::render::view::NoFrustumCulling;
-use crate::common::MaterialWizard;
 use crate::event_exists;
-use crate::dino_run::character::{
-    spawn_legs, animate_legs, AnimationState, spawn_body, animate_tail, spawn_neck_and_head
+use crate::dino_run::state::{
+    DinoRunState, GameOverGrace, ResetRun, start_game_over_grace, start_run, restart_run, toggle_pause, pause_on_focus_loss, pause_time, resume_time,
+    end_run, reset_run
 };
-use crate::dino_run::environment::{spawn_cave_tunnel, insert_crystal_stuff, spawn_crystals, update_lights};
-use crate::dino_run::audio::{setup_audio, jump_audio, score_audio, hurt_audio};
+use crate::dino_run::input::{Action, InputBindings, load_input_bindings, update_actions};
+use crate::dino_run::difficulty::apply_difficulty;
+use crate::dino_run::config::{DinoRunConfig, ConfigFile, apply_max_lives, reload_config};
+use crate::dino_run::stats::{RunStats, track_run_stats, reset_run_stats};
+use crate::dino_run::high_scores::{load_high_scores, record_high_score};
+use crate::dino_run::visuals::DinoRunVisualsPlugin;
+use crate::dino_run::settings::{Settings, load_settings, save_settings, apply_audio_settings};
+use crate::common::Mixer;
+use crate::dino_run::audio::{
+    setup_audio, apply_mixer, pause_music, resume_music, update_music, adapt_music, duck_music, game_over_music,
+    stop_sting, jump_audio, score_audio, hurt_audio
+};
+use crate::dino_run::seed::{RunSeed, reroll_seed};
+use crate::dino_run::replay::{
+    ReplaySettings, ReplayRecorder, ReplayPlayer, setup_replay, record_input, play_back_input, start_replay,
+    save_recording, check_playback, reset_replay
+};
+use crate::dino_run::collision::{
+    Collider, Hitbox, spawn_hitbox, pose_hitbox, hitbox_pose, obstacle_part_pose, intersects
+};
+use crate::dino_run::obstacles::ObstacleCatalogue;
 use fastrand::Rng;
 
+// the whole game: the simulation, everything drawn on top of it, sound and the high score table
 pub struct DinoRunPlugin;
 impl Plugin for DinoRunPlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins((DinoRunSimPlugin, DinoRunVisualsPlugin));
+        app.add_systems(Startup, load_high_scores);
+        app.add_systems(Startup, load_input_bindings);
+        // the tuning is part of a replay, so it stays put while one is recorded or played
+        app.add_systems(Update, reload_config.run_if(resource_exists::<ConfigFile>
+            .and(not(resource_exists::<ReplayRecorder>))
+            .and(not(resource_exists::<ReplayPlayer>))));
+        // a replayed run is already in the table from when it was played
+        app.add_systems(OnEnter(DinoRunState::GameOver), record_high_score.run_if(
+            not(resource_exists::<ReplayPlayer>)));
+        app.add_systems(Startup, (load_settings, setup_audio).chain());
+        app.init_resource::<Mixer>();
+        app.add_systems(Update, (
+            apply_audio_settings.run_if(resource_changed::<Settings>),
+            apply_mixer.run_if(resource_changed::<Mixer>)
+        ).chain());
+        app.add_systems(Update, save_settings.run_if(
+            resource_changed::<Settings>.and(not(resource_added::<Settings>))));
+        app.add_systems(OnEnter(DinoRunState::Paused), pause_music);
+        app.add_systems(OnExit(DinoRunState::Paused), resume_music);
+        app.add_systems(Update, adapt_music.run_if(in_state(DinoRunState::Running)));
+        app.add_systems(Update, update_music.after(adapt_music));
+        app.add_systems(OnEnter(DinoRunState::GameOver), game_over_music);
+        app.add_systems(OnExit(DinoRunState::GameOver), stop_sting);
+        app.add_systems(Update, pause_on_focus_loss.run_if(in_state(DinoRunState::Running)));
+        app.add_systems(PostUpdate, jump_audio.run_if(event_exists!(PlayerJumps)));
+        app.add_systems(PostUpdate, score_audio.run_if(event_exists!(PlayerScores)));
+        app.add_systems(PostUpdate, hurt_audio.run_if(event_exists!(PlayerHurt)));
+        app.add_systems(PostUpdate, duck_music.run_if(event_exists!(PlayerHurt)));
+    }
+}
+
+// just the gameplay: physics, obstacles, collision, scoring and hurt timing, with no meshes, windows
+// or audio, so it runs under MinimalPlugins (plus StatesPlugin and InputPlugin)
+pub struct DinoRunSimPlugin;
+impl Plugin for DinoRunSimPlugin {
+    fn build(&self, app: &mut App) {
+        let running = in_state(DinoRunState::Running);
+        app.init_state::<DinoRunState>();
+        // the defaults until DinoRunPlugin loads the player's own, so headless runs never read the file
+        app.init_resource::<InputBindings>();
+        app.init_resource::<ButtonInput<Action>>();
+        app.add_systems(PreUpdate, update_actions.after(bevy::input::InputSystem));
+        app.init_resource::<GameOverGrace>();
+        app.add_systems(OnEnter(DinoRunState::GameOver), start_game_over_grace);
+        app.add_systems(Update, start_run.run_if(
+            in_state(DinoRunState::Title).or(in_state(DinoRunState::GameOver))));
+        app.add_systems(Update, restart_run.run_if(
+            in_state(DinoRunState::Running).or(in_state(DinoRunState::Paused))));
+        app.add_systems(Update, toggle_pause);
+        app.add_systems(OnEnter(DinoRunState::Paused), pause_time);
+        app.add_systems(OnExit(DinoRunState::Paused), resume_time);
+        app.add_systems(Update, end_run.run_if(event_exists!(PlayerDied)));
+        app.init_resource::<RunSeed>();
+        app.add_systems(OnExit(DinoRunState::GameOver), reset_run);
+        app.add_systems(ResetRun,
+            (reroll_seed, (reset_player, reset_obstacles, reset_run_stats, reset_replay)).chain());
+        app.insert_resource(Time::<Fixed>::from_hz(PHYSICS_HZ));
         app.add_systems(Startup, spawn_player);
-        app.add_systems(PreUpdate, player_jump_system);
+        app.init_resource::<JumpInput>();
+        app.add_systems(PreUpdate, read_jump_input.after(update_actions).run_if(
+            running.clone().and(not(resource_exists::<ReplayPlayer>))));
+        app.init_resource::<ReplaySettings>();
+        app.add_systems(Startup, setup_replay.before(insert_obstacle_resources));
+        app.add_systems(Update, start_replay.run_if(
+            in_state(DinoRunState::Title).and(resource_exists::<ReplayPlayer>)));
+        app.add_systems(OnEnter(DinoRunState::GameOver), (
+            save_recording.run_if(resource_exists::<ReplayRecorder>),
+            check_playback.run_if(resource_exists::<ReplayPlayer>)
+        ));
         app.add_systems(Startup, insert_obstacle_resources);
         app.add_event::<SpawnObstacle>();
-        app.add_systems(FixedPreUpdate, obstacle_spawn_timing);
-        app.add_systems(FixedPreUpdate, obstacle_spawner.after(obstacle_spawn_timing).run_if(event_exists!(SpawnObstacle)));
-        app.add_systems(Update, update_obstacles);
         app.add_event::<PlayerJumps>();
         app.add_event::<PlayerHurt>();
         app.add_event::<PlayerScores>();
-        app.insert_resource(LevelSpeed {f32: 5.0});
-        app.add_systems(Update, animate_legs);
-        app.add_systems(Update, animate_tail);
-        app.add_systems(Startup, spawn_cave_tunnel);
-        app.add_systems(Startup, insert_crystal_stuff);
-        app.add_systems(Update, (spawn_crystals, update_lights).chain());
+        app.add_event::<PlayerDied>();
+        // the defaults, unless main has already loaded the tuning file
+        app.init_resource::<DinoRunConfig>();
+        app.add_systems(Update, apply_max_lives.run_if(resource_changed::<DinoRunConfig>));
+        app.init_resource::<ObstacleCatalogue>();
+        app.insert_resource(LevelSpeed {f32: DinoRunConfig::default().difficulty.start_speed});
+        app.insert_resource(PlayerHealth::new(DinoRunConfig::default().max_lives));
+        app.init_resource::<RunStats>();
         app.insert_resource(HurtCounters{total_remaining: 0, flick: 0, should_show: true});
-        app.add_systems(FixedPreUpdate, hurt_manager);
-        app.add_systems(Startup, setup_audio);
-        app.add_systems(PostUpdate, jump_audio.run_if(event_exists!(PlayerJumps)));
-        app.add_systems(PostUpdate, score_audio.run_if(event_exists!(PlayerScores)));
-        app.add_systems(PostUpdate, hurt_audio.run_if(event_exists!(PlayerHurt)));
+        app.add_systems(FixedPreUpdate, hurt_manager.run_if(running.clone()));
+        // a long frame can hold several steps, the ones after the dino dies are skipped until GameOver
+        app.add_systems(FixedUpdate, (
+            apply_difficulty,
+            record_input.run_if(resource_exists::<ReplayRecorder>),
+            play_back_input.run_if(resource_exists::<ReplayPlayer>),
+            player_jump_system,
+            pose_hitbox,
+            update_obstacles,
+            despawn_obstacles,
+            (health_manager, track_run_stats),
+            obstacle_spawn_timing,
+            obstacle_spawner.run_if(event_exists!(SpawnObstacle))
+        ).chain().run_if(running.and(|health: Res<PlayerHealth>| !health.is_dead())));
     }
 }
 
-#[derive(Component)]
+// z and velocity are the fixed step's state, the Transform only shows it interpolated between steps
+#[derive(Component, Clone, Debug)]
 pub struct Player{
-    pub velocity: f32
-}
-
-#[derive(Resource)]
-pub struct PlayerEntity {
-    entity: Entity
+    pub velocity: f32,
+    pub z: f32,
+    pub previous_z: f32,
+    pub airborne: bool,
+    pub ducking: bool,  // crouched on the ground, or fast-falling in the air
+    pub jump_buffer: f32,  // seconds a jump press that couldn't be used yet stays live
+    pub coyote: f32  // seconds since leaving the ground without jumping that a jump still works
+} impl Player {
+    const GROUNDED: Player = Player{
+        velocity: 0.0, z: 0.0, previous_z: 0.0, airborne: false, ducking: false, jump_buffer: 0.0, coyote: 0.0
+    };
 }
 
 fn spawn_player(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut commands: Commands
 ) {
     let player = commands.spawn(
         (
-            Player{velocity: 0.0},
-            Transform::default(),
-            Visibility::Visible
+            Player::GROUNDED,
+            Transform::default()
         )
     ).id();
-    let hip = spawn_legs(
-        player,
-        &mut commands,
-        &mut meshes,
-        &mut materials
-    );
-    let body = spawn_body(
-        hip,
-        &mut commands,
-        &mut meshes,
-        &mut materials
-    );
-    spawn_neck_and_head(
-        body,
-        &mut commands,
-        &mut meshes,
-        &mut materials
-    );
-    commands.insert_resource(PlayerEntity{entity: player});
+    spawn_hitbox(player, &mut commands);
 }
 
 #[derive(Event)]
 pub struct PlayerJumps;
 
-pub const JUMP_V: f32 = 10.0;
+pub const DUCK_HIP_DROP: f32 = 0.4;
+pub const DUCK_HEAD_DROP: f32 = 0.15;  // on top of the hip drop
 
-fn player_jump_system(
-    mut query: Query<(&mut Transform, &mut Player)>,
-    keys: Res<ButtonInput<KeyCode>>,
+pub const PHYSICS_HZ: f64 = 64.0;
+
+// presses are latched here every frame, since a frame can run zero or several fixed steps
+#[derive(Resource, Default)]
+pub struct JumpInput {
+    pub pressed: bool,
+    pub held: bool,
+    pub duck: bool
+}
+
+fn read_jump_input(
+    actions: Res<ButtonInput<Action>>,
+    mut jump_input: ResMut<JumpInput>
+) {
+    if actions.just_pressed(Action::Jump) {
+        jump_input.pressed = true;
+    };
+    jump_input.held = actions.pressed(Action::Jump);
+    jump_input.duck = actions.pressed(Action::Duck);
+}
+
+pub fn player_jump_system(
+    mut query: Query<&mut Player>,
+    mut jump_input: ResMut<JumpInput>,
+    config: Res<DinoRunConfig>,
     time: Res<Time>,
-    mut animation_state: ResMut<AnimationState>,
     mut event_writer: EventWriter<PlayerJumps>
 ) {
     // statics
     let dt = time.delta_secs();
-    let g = 40.0 * dt;
-    let f = g / 2.0;
     // inputs
-    let jumped = keys.just_pressed(KeyCode::Space)
-        || keys.just_pressed(KeyCode::ArrowUp)
-        || keys.just_pressed(KeyCode::KeyW);
-    let held = keys.pressed(KeyCode::Space)
-        || keys.pressed(KeyCode::ArrowUp)
-        || keys.pressed(KeyCode::KeyW);
+    let jumped = std::mem::take(&mut jump_input.pressed);
+    let held = jump_input.held;
+    let duck = jump_input.duck;
     // query
-    if let Ok((mut t, mut p)) = query.single_mut() {
-        // jump key
-        if t.translation.z < 0.25 && p.velocity <= 0.0 && jumped {
-            p.velocity = JUMP_V;
-            animation_state.jumping = true;
+    if let Ok(mut p) = query.single_mut() {
+        p.previous_z = p.z;
+        p.ducking = duck;
+        // jump key, remembered for a moment if it comes too early and honoured for a moment if too late
+        p.jump_buffer = (p.jump_buffer - dt).max(0.0);
+        p.coyote = (p.coyote - dt).max(0.0);
+        if jumped {
+            p.jump_buffer = config.jump_buffer;
+        };
+        let standing = p.z <= 0.0 && p.velocity <= 0.0;
+        if standing {
+            p.coyote = config.coyote_time;
+        };
+        if (jumped || p.jump_buffer > 0.0) && (standing || p.coyote > 0.0) && p.velocity <= 0.0 {
+            p.velocity = config.jump_v;
+            p.airborne = true;
+            p.jump_buffer = 0.0;
+            p.coyote = 0.0;
             event_writer.write(PlayerJumps);
         };
+        // ducking in the air gives up the rest of the climb and drops fast
+        if p.airborne && duck {
+            p.velocity = p.velocity.min(0.0);
+        };
         // gravity, hold to jump higher
-        let float = held && p.velocity > 0.0;
-        if t.translation.z > 0.0 {
-            if float {
-                p.velocity -= f;
+        if p.z > 0.0 || p.velocity > 0.0 {
+            let float = held && p.velocity > 0.0;
+            let a = if float {
+                -config.gravity * config.hold_gravity_scale
+            } else if p.airborne && duck {
+                -config.gravity * config.fast_fall_gravity_scale
             } else {
-                p.velocity -= g;
+                -config.gravity
             };
-        };
-        // apply velocity
-        if p.velocity != 0.0 {
-            t.translation.z += p.velocity * dt;
+            // constant acceleration across the step, so the arc doesn't depend on the step size
+            p.z += p.velocity * dt + 0.5 * a * dt * dt;
+            p.velocity += a * dt;
         };
         // catch falling player
-        if p.velocity < 0.0 && t.translation.z <= 0.0 {
-            t.translation.z = 0.0;
+        if p.velocity < 0.0 && p.z <= 0.0 {
+            p.z = 0.0;
             p.velocity = 0.0;
-            animation_state.jumping = false;
+            p.airborne = false;
         };
     };
 }
 
-#[derive(Resource)]
-struct ObstacleAssets {
-    hex_mesh: Handle<Mesh>,
-    wizard: MaterialWizard,
-    hues: Vec<f32>
+fn reset_player(
+    mut query: Query<(&mut Transform, &mut Player)>,
+    mut jump_input: ResMut<JumpInput>,
+    mut hurt_counters: ResMut<HurtCounters>,
+    mut health: ResMut<PlayerHealth>
+) {
+    if let Ok((mut t, mut p)) = query.single_mut() {
+        t.translation = Vec3::ZERO;
+        *p = Player::GROUNDED;
+    };
+    *jump_input = JumpInput::default();
+    *hurt_counters = HurtCounters{total_remaining: 0, flick: 0, should_show: true};
+    health.reset();
 }
 
 #[derive(Resource)]
@@ -146,196 +268,275 @@ struct ObstacleRng {
     rng: Rng
 }
 
-const COLOR_COUNT: usize = 16;
-
 fn insert_obstacle_resources(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    time: Res<Time>
+    run_seed: Res<RunSeed>,
+    config: Res<DinoRunConfig>
 ) {
-    let wizard = {
-        let saturation = 1.0;
-        let lightness = 0.5;
-        let alpha = 0.5;
-        let color_count = COLOR_COUNT;
-        let crystal_mat = StandardMaterial {
-            base_color: Color::WHITE.with_alpha(alpha),
-            perceptual_roughness: 0.2,
-            reflectance: 0.6,
-            diffuse_transmission: 0.3,
-            ..default()
-        };
-        MaterialWizard::new(
-            &mut materials, crystal_mat, saturation,
-            lightness, alpha, color_count, 0.5, false,
-        )
-    };
-    let hues: Vec<f32> = {
-        let normals = MaterialWizard::generate_normal_hue_vec(COLOR_COUNT);
-        let mut hues: Vec<f32> = Vec::with_capacity(COLOR_COUNT);
-        for i in 0..COLOR_COUNT {
-            hues.push(normals[i] * 360.0);
-        };
-        hues
-    };
-    let hex = meshes.add(
-        Extrusion::new(
-            RegularPolygon::new(0.75, 6), 1.0
-        )
-    );
-    let obs_assets = ObstacleAssets {
-        hex_mesh: hex,
-        wizard,
-        hues
+    commands.insert_resource(ObstacleTimer::new(&config));
+    commands.insert_resource(ObstacleRng{rng: run_seed.obstacle_rng()});
+}
+
+fn reset_obstacles(
+    mut commands: Commands,
+    obstacle_query: Query<Entity, With<Obstacle>>,
+    mut obstacle_timer: ResMut<ObstacleTimer>,
+    mut obstacle_rng: ResMut<ObstacleRng>,
+    run_seed: Res<RunSeed>,
+    config: Res<DinoRunConfig>
+) {
+    for en in obstacle_query {
+        commands.entity(en).despawn();
     };
-    commands.insert_resource(obs_assets);
-    commands.insert_resource(ObstacleTimer{next: time.elapsed_secs() + 1.0, count: 0});
-    commands.insert_resource(ObstacleRng{rng: Rng::new()});
+    *obstacle_timer = ObstacleTimer::new(&config);
+    obstacle_rng.rng = run_seed.obstacle_rng();
 }
 
 #[derive(Event)]
 struct SpawnObstacle{
-    count: u32
+    count: u32,
+    x: f32,
+    kind: usize
 }
 
+// like CrystalTimer, next_x scrolls with the level and an obstacle is placed once it reaches the spawn point
 #[derive(Resource)]
 struct ObstacleTimer {
-    next: f32,
+    next_x: f32,
+    next_kind: usize,  // the first obstacle of a run is always the catalogue's first kind
     count: u32
+} impl ObstacleTimer {
+    fn new(config: &DinoRunConfig) -> Self {
+        Self {next_x: config.spawn_x + config.lead_in, next_kind: 0, count: 0}
+    }
 }
 
-const OBSTACLE_DELAY: f32 = 2.0;
-
+#[allow(clippy::too_many_arguments)]
 fn obstacle_spawn_timing(
     time: Res<Time>,
     mut obstacle_timer: ResMut<ObstacleTimer>,
     mut event_writer: EventWriter<SpawnObstacle>,
-    mut obstacle_rng: ResMut<ObstacleRng>
+    mut obstacle_rng: ResMut<ObstacleRng>,
+    config: Res<DinoRunConfig>,
+    stats: Res<RunStats>,
+    speed: Res<LevelSpeed>,
+    catalogue: Res<ObstacleCatalogue>
 ) {
-    let t = time.elapsed_secs();
-    if t >= obstacle_timer.next {
-        event_writer.write(SpawnObstacle{count: obstacle_timer.count});
-        let r = obstacle_rng.rng.f32() - 0.5;
-        obstacle_timer.next = t + OBSTACLE_DELAY + OBSTACLE_DELAY * r;
-        obstacle_timer.count +=1;
+    obstacle_timer.next_x -= time.delta_secs() * speed.f32;
+    let curve = &config.difficulty;
+    let airtime = config.jump_airtime();
+    while obstacle_timer.next_x <= config.spawn_x {
+        let kind = obstacle_timer.next_kind;
+        event_writer.write(SpawnObstacle{count: obstacle_timer.count, x: obstacle_timer.next_x, kind});
+        let r = obstacle_rng.rng.f32();
+        let (next_kind, gap) = match catalogue.kinds[kind].follow_up {
+            Some(next_kind) => (next_kind, curve.tight_gap(speed.f32, airtime)),
+            None => (
+                catalogue.pick(obstacle_rng.rng.f32(), speed.f32),
+                curve.next_gap(&stats, speed.f32, airtime, r)
+            )
+        };
+        obstacle_timer.next_x += gap + catalogue.padding(kind, next_kind);
+        obstacle_timer.next_kind = next_kind;
+        obstacle_timer.count += 1;
     };
 }
 
+// x is the fixed step's position, like Player::z, its ObstacleParts hang off it as children
 #[derive(Component)]
-struct Obstacle {
-    radius: f32,
-    height: f32,
-    scored: bool
+pub struct Obstacle {
+    pub scored: bool,
+    pub x: f32,
+    pub previous_x: f32,
+    pub reach: f32,  // how far the shapes stick out in front of x
+    pub kind: usize,
+    pub count: u32  // spawn order within the run
+}
+
+#[derive(Component)]
+pub struct ObstaclePart {
+    pub index: usize  // into the kind's shapes
+}
+
+pub fn spawn_obstacle(
+    commands: &mut Commands,
+    catalogue: &ObstacleCatalogue,
+    kind: usize,
+    x: f32,
+    count: u32,
+    rng: &mut Rng
+) -> Entity {
+    let obstacle_kind = &catalogue.kinds[kind];
+    let obstacle = commands.spawn(
+        (
+            Transform::from_xyz(x, 0.0, 0.0),
+            Obstacle {scored: false, x, previous_x: x, reach: obstacle_kind.reach(1.0), kind, count}
+        )
+    ).id();
+    for (index, shape) in obstacle_kind.shapes.iter().enumerate() {
+        commands.spawn(
+            (
+                Transform::from_translation(shape.offset).with_rotation(Quat::from_rotation_z(rng.f32())),
+                ObstaclePart {index},
+                shape.collider,
+                ChildOf(obstacle)
+            )
+        );
+    };
+    obstacle
 }
 
 fn obstacle_spawner(
     mut event_reader: EventReader<SpawnObstacle>,
-    assets: Res<ObstacleAssets>,
     mut commands: Commands,
     mut obstacle_rng: ResMut<ObstacleRng>,
-    obstacle_query: Query<(&Transform, Entity), With<Obstacle>>
+    catalogue: Res<ObstacleCatalogue>
 ) {
     for event in event_reader.read() {
-        let i = event.count as usize % COLOR_COUNT;
-        commands.spawn(
-            (
-                Transform::from_xyz(15.0, 0.0, 0.49).with_rotation(Quat::from_rotation_z(obstacle_rng.rng.f32())),
-                Mesh3d(assets.hex_mesh.clone()),
-                MeshMaterial3d(assets.wizard.get_index(i)),
-                Obstacle {
-                    radius: 0.75,
-                    height: 1.25,
-                    scored: false
-                },
-                NotShadowCaster,
-                NotShadowReceiver,
-                NoFrustumCulling,
-                PointLight {
-                    color: Color::hsl(assets.hues[i], 1.0, 0.6),
-                    intensity: 32000.0,
-                    range: 10.0,
-                    shadows_enabled: true,
-                    radius: 0.5,
-                    shadow_map_near_z: 1.0,
-                    ..default()
-                }
-            )
-        );
+        spawn_obstacle(&mut commands, &catalogue, event.kind, event.x, event.count, &mut obstacle_rng.rng);
     };
-    for (t, en) in obstacle_query {
-        if t.translation.x < -7.0 {
+}
+
+fn despawn_obstacles(
+    mut commands: Commands,
+    obstacle_query: Query<(&Obstacle, Entity)>,
+    config: Res<DinoRunConfig>
+) {
+    for (obstacle, en) in obstacle_query {
+        if obstacle.x < config.despawn_x {
             commands.entity(en).despawn()
         };
     };
 }
 
+// x is where the obstacle was, so its sound can come from that side
 #[derive(Event)]
-pub struct PlayerHurt;
+pub struct PlayerHurt {
+    pub x: f32
+}
 
 #[derive(Event)]
-pub struct PlayerScores;
+pub struct PlayerScores {
+    pub x: f32
+}
 
 #[derive(Resource)]
 pub struct LevelSpeed {
     pub f32: f32
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_obstacles (
-    mut transform_query: Query<&mut Transform>,
-    mut obstacle_query: Query<(&mut Obstacle, Entity)>,
-    p_entity: Res<PlayerEntity>,
+    mut obstacle_query: Query<(&mut Obstacle, &Children)>,
+    part_query: Query<(&Collider, &Transform), With<ObstaclePart>>,
+    player_query: Query<&Player>,
+    hitbox_query: Query<(&Collider, &Transform), With<Hitbox>>,
     time: Res<Time>,
     speed: Res<LevelSpeed>,
+    hurt_counters: Res<HurtCounters>,
     mut hit_writer: EventWriter<PlayerHurt>,
     mut score_writer: EventWriter<PlayerScores>
 ) {
     let dt = time.delta_secs();
     let motion = dt * speed.f32;
-    let player_z = transform_query.get(p_entity.entity).unwrap().translation.z;
-    for (mut obstacle, entity) in &mut obstacle_query {
-        let mut transform = transform_query.get_mut(entity).unwrap();
-        transform.translation.x -= motion;
+    let player_z = player_query.single().unwrap().z;
+    let hitboxes: Vec<(&Collider, Isometry3d)> = hitbox_query.iter()
+        .map(|(collider, local)| (collider, hitbox_pose(player_z, local)))
+        .collect();
+    // an obstacle scores once it's behind every part of the dino, tail included
+    let back = hitboxes.iter()
+        .map(|(collider, pose)| pose.translation.x - collider.bounding_radius())
+        .fold(0.0, f32::min);
+    // a hit during the flicker window passes straight through the dino
+    let mut invulnerable = hurt_counters.total_remaining > 0;
+    for (mut obstacle, children) in &mut obstacle_query {
+        obstacle.previous_x = obstacle.x;
+        obstacle.x -= motion;
         if obstacle.scored {
             continue;
-        } else if transform.translation.x.abs() < obstacle.radius && player_z < obstacle.height {
-            println!("Hit!!");
+        };
+        let hit = part_query.iter_many(children).any(|(collider, local)| {
+            let pose = obstacle_part_pose(obstacle.x, local);
+            let reach = collider.bounding_radius();
+            hitboxes.iter().any(|(part, part_pose)| {
+                (part_pose.translation.x - pose.translation.x).abs() < part.bounding_radius() + reach
+                    && intersects(part, *part_pose, collider, pose)
+            })
+        });
+        if hit {
             obstacle.scored = true;
-            hit_writer.write(PlayerHurt);
-        } else if transform.translation.x < -0.0 {
+            if invulnerable {
+                continue;
+            };
+            invulnerable = true;
+            hit_writer.write(PlayerHurt {x: obstacle.x});
+        } else if obstacle.x + obstacle.reach < back {
             obstacle.scored = true;
-            println!("Score!!");
-            score_writer.write(PlayerScores);
+            score_writer.write(PlayerScores {x: obstacle.x});
         };
     };
 }
 
+// ticked on the fixed step, should_show is what the flicker wants the dino's visibility to be
 #[derive(Resource)]
-struct HurtCounters {
-    total_remaining: u8,
-    flick: u8,
-    should_show: bool
+pub struct HurtCounters {
+    pub total_remaining: u8,
+    pub flick: u8,
+    pub should_show: bool
 }
 
-const FLASH_DUR: u8 = 40;
-const FLICK_DUR: u8 = 4;
+#[derive(Event)]
+pub struct PlayerDied;
 
-fn hurt_manager(
-    mut vis_query: Query<&mut Visibility, With<Player>>,
+#[derive(Resource)]
+pub struct PlayerHealth {
+    pub max_lives: u8,
+    pub lives: u8
+} impl PlayerHealth {
+    pub fn new(max_lives: u8) -> Self {
+        Self {max_lives, lives: max_lives}
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.lives == 0
+    }
+
+    pub fn reset(&mut self) {
+        self.lives = self.max_lives;
+    }
+}
+
+fn health_manager(
+    mut event_reader: EventReader<PlayerHurt>,
+    mut health: ResMut<PlayerHealth>,
     mut hurt_counters: ResMut<HurtCounters>,
-    mut event_reader: EventReader<PlayerHurt>
+    mut event_writer: EventWriter<PlayerDied>,
+    config: Res<DinoRunConfig>
 ) {
-    let mut v = vis_query.single_mut().unwrap();
-    let is_visible = match v.clone() {
-        Visibility::Visible => true,
-        _ => false
+    for _ in event_reader.read() {
+        // start the invulnerability window now, hurt_manager only catches up on the next fixed tick
+        hurt_counters.total_remaining = config.flash_dur;
+        if health.is_dead() {
+            continue;
+        };
+        health.lives -= 1;
+        if health.is_dead() {
+            event_writer.write(PlayerDied);
+        };
     };
+}
+
+fn hurt_manager(
+    mut hurt_counters: ResMut<HurtCounters>,
+    mut event_reader: EventReader<PlayerHurt>,
+    config: Res<DinoRunConfig>
+) {
     for _ in event_reader.read() {
-        hurt_counters.total_remaining = FLASH_DUR;
+        hurt_counters.total_remaining = config.flash_dur;
     };
     if hurt_counters.total_remaining > 0 {
         if hurt_counters.flick == 0 {
-            hurt_counters.flick = FLICK_DUR;
+            hurt_counters.flick = config.flick_dur;
             hurt_counters.should_show = !hurt_counters.should_show;
         } else if hurt_counters.flick > 0 {
             hurt_counters.flick -= 1;
@@ -345,7 +546,4 @@ fn hurt_manager(
         hurt_counters.should_show = true;
         hurt_counters.flick = 0;
     };
-    if is_visible != hurt_counters.should_show {
-        v.toggle_visible_hidden();
-    };
 }
\ No newline at end of file
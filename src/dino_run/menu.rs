@@ -0,0 +1,275 @@
+use bevy::prelude::*;
+use crate::dino_run::settings::Settings;
+use crate::dino_run::state::{DinoRunState, ResetRun};
+
+const MENU_FONT_SIZE: f32 = 32.0;
+const SETTINGS_FONT_SIZE: f32 = 24.0;
+const BUTTON_WIDTH: f32 = 260.0;
+const BUTTON_HEIGHT: f32 = 56.0;
+const SETTINGS_BUTTON_WIDTH: f32 = 360.0;
+const SETTINGS_BUTTON_HEIGHT: f32 = 40.0;
+const BUTTON_IDLE: Color = Color::srgba(0.1, 0.1, 0.15, 0.8);
+const BUTTON_HOVERED: Color = Color::srgba(0.25, 0.25, 0.35, 0.9);
+const BUTTON_PRESSED: Color = Color::srgba(0.45, 0.35, 0.6, 0.9);
+const VOLUME_STEPS: f32 = 10.0;
+
+#[derive(Component)]
+pub struct PauseMenu;
+
+// which set of buttons the pause menu is showing
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuPage {
+    #[default]
+    Main,
+    Settings
+}
+
+#[derive(Component, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum MenuButton {
+    Resume,
+    Restart,
+    Settings,
+    Quit
+} impl MenuButton {
+    const ALL: [MenuButton; 4] = [
+        Self::Resume,
+        Self::Restart,
+        Self::Settings,
+        Self::Quit
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Resume => "Resume",
+            Self::Restart => "Restart",
+            Self::Settings => "Settings",
+            Self::Quit => "Quit"
+        }
+    }
+}
+
+// each one steps or flips its setting when clicked, and its text shows the current value
+#[derive(Component, Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SettingsButton {
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Bloom,
+    Msaa,
+    Shadows,
+    Hdr,
+    Fullscreen,
+    Vsync,
+    Back
+} impl SettingsButton {
+    const ALL: [SettingsButton; 10] = [
+        Self::MasterVolume,
+        Self::MusicVolume,
+        Self::SfxVolume,
+        Self::Bloom,
+        Self::Msaa,
+        Self::Shadows,
+        Self::Hdr,
+        Self::Fullscreen,
+        Self::Vsync,
+        Self::Back
+    ];
+
+    fn label(self, settings: &Settings) -> String {
+        let on_off = |on: bool| if on {"On"} else {"Off"};
+        match self {
+            Self::MasterVolume => format!("Volume: {:.0}%", settings.master_volume * 100.0),
+            Self::MusicVolume => format!("Music: {:.0}%", settings.music_volume * 100.0),
+            Self::SfxVolume => format!("Sound effects: {:.0}%", settings.sfx_volume * 100.0),
+            Self::Bloom => format!("Bloom: {}", on_off(settings.bloom)),
+            Self::Msaa => format!("Anti-aliasing: {}", on_off(settings.msaa)),
+            Self::Shadows => format!("Light shadows: {}", on_off(settings.shadows)),
+            Self::Hdr => format!("HDR: {}", on_off(settings.hdr)),
+            Self::Fullscreen => format!("Fullscreen: {}", on_off(settings.fullscreen)),
+            Self::Vsync => format!("Vsync: {}", on_off(settings.vsync)),
+            Self::Back => "Back".to_string()
+        }
+    }
+
+    fn press(self, settings: &mut Settings) {
+        // volumes go up a step at a time and wrap round to silent
+        let step = |volume: &mut f32| {
+            *volume = ((*volume * VOLUME_STEPS).round() + 1.0) % (VOLUME_STEPS + 1.0) / VOLUME_STEPS;
+        };
+        match self {
+            Self::MasterVolume => step(&mut settings.master_volume),
+            Self::MusicVolume => step(&mut settings.music_volume),
+            Self::SfxVolume => step(&mut settings.sfx_volume),
+            Self::Bloom => settings.bloom = !settings.bloom,
+            Self::Msaa => settings.msaa = !settings.msaa,
+            Self::Shadows => settings.shadows = !settings.shadows,
+            Self::Hdr => settings.hdr = !settings.hdr,
+            Self::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            Self::Vsync => settings.vsync = !settings.vsync,
+            Self::Back => {}
+        };
+    }
+}
+
+#[derive(Component)]
+pub struct SettingsLabel(SettingsButton);
+
+fn spawn_button(
+    commands: &mut Commands,
+    parent: Entity,
+    size: Vec2,
+    font_size: f32,
+    label: String
+) -> (Entity, Entity) {
+    let button = commands.spawn(
+        (
+            Button,
+            Node {
+                width: Val::Px(size.x),
+                height: Val::Px(size.y),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_IDLE),
+            ChildOf(parent)
+        )
+    ).id();
+    let text = commands.spawn(
+        (
+            Text::new(label),
+            TextFont {
+                font_size,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            ChildOf(button)
+        )
+    ).id();
+    (button, text)
+}
+
+pub fn reset_menu_page(
+    mut page: ResMut<MenuPage>
+) {
+    page.set_if_neq(MenuPage::Main);
+}
+
+// throws away whatever menu is up and builds the current page
+pub fn spawn_pause_menu(
+    mut commands: Commands,
+    query: Query<Entity, With<PauseMenu>>,
+    page: Res<MenuPage>,
+    settings: Res<Settings>
+) {
+    for entity in query {
+        commands.entity(entity).despawn();
+    };
+    // the settings list is longer, so it starts higher up
+    let (top, gap) = match *page {
+        MenuPage::Main => (35.0, 12.0),
+        MenuPage::Settings => (28.0, 6.0)
+    };
+    let column = commands.spawn(
+        (
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                top: Val::Percent(top),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(gap),
+                ..default()
+            },
+            PauseMenu
+        )
+    ).id();
+    match *page {
+        MenuPage::Main => for button in MenuButton::ALL {
+            let size = Vec2::new(BUTTON_WIDTH, BUTTON_HEIGHT);
+            let label = button.label().to_string();
+            let (entity, _) = spawn_button(&mut commands, column, size, MENU_FONT_SIZE, label);
+            commands.entity(entity).insert(button);
+        },
+        MenuPage::Settings => {
+            for button in SettingsButton::ALL {
+                let size = Vec2::new(SETTINGS_BUTTON_WIDTH, SETTINGS_BUTTON_HEIGHT);
+                let label = button.label(&settings);
+                let (entity, text) = spawn_button(&mut commands, column, size, SETTINGS_FONT_SIZE, label);
+                commands.entity(entity).insert(button);
+                commands.entity(text).insert(SettingsLabel(button));
+            };
+        }
+    };
+}
+
+pub fn despawn_pause_menu(
+    mut commands: Commands,
+    query: Query<Entity, With<PauseMenu>>
+) {
+    for entity in query {
+        commands.entity(entity).despawn();
+    };
+}
+
+pub fn highlight_buttons(
+    mut query: Query<(&Interaction, &mut BackgroundColor), Changed<Interaction>>
+) {
+    for (interaction, mut background) in &mut query {
+        background.0 = match interaction {
+            Interaction::Pressed => BUTTON_PRESSED,
+            Interaction::Hovered => BUTTON_HOVERED,
+            Interaction::None => BUTTON_IDLE
+        };
+    };
+}
+
+pub fn pause_menu_buttons(
+    query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut commands: Commands,
+    mut page: ResMut<MenuPage>,
+    mut next_state: ResMut<NextState<DinoRunState>>,
+    mut app_exit: EventWriter<AppExit>
+) {
+    for (interaction, button) in query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        };
+        match button {
+            MenuButton::Resume => next_state.set(DinoRunState::Running),
+            MenuButton::Restart => {
+                commands.run_schedule(ResetRun);
+                next_state.set(DinoRunState::Running);
+            },
+            MenuButton::Settings => *page = MenuPage::Settings,
+            MenuButton::Quit => {
+                app_exit.write(AppExit::Success);
+            }
+        };
+    };
+}
+
+pub fn settings_buttons(
+    query: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut page: ResMut<MenuPage>,
+    mut settings: ResMut<Settings>
+) {
+    for (interaction, button) in query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        };
+        match button {
+            SettingsButton::Back => *page = MenuPage::Main,
+            _ => button.press(&mut settings)
+        };
+    };
+}
+
+pub fn update_settings_labels(
+    mut query: Query<(&mut Text, &SettingsLabel)>,
+    settings: Res<Settings>
+) {
+    for (mut text, label) in &mut query {
+        **text = label.0.label(&settings);
+    };
+}
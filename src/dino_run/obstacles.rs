@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+use crate::dino_run::collision::Collider;
+
+pub const OBSTACLE_RADIUS: f32 = 0.75;
+pub const OBSTACLE_HEIGHT: f32 = 1.0;
+
+// the lowest point of a stalactite, the dino has to duck to get under it
+pub const STALACTITE_BOTTOM: f32 = 1.1;
+const STALACTITE_LENGTH: f32 = 4.0;
+
+// one crystal of an obstacle, offset from the obstacle's x and the ground, with its own random spin
+#[derive(Clone, Copy, Debug)]
+pub struct ObstacleShape {
+    pub collider: Collider,
+    pub offset: Vec3
+}
+
+#[derive(Clone, Debug)]
+pub struct ObstacleKind {
+    pub name: &'static str,
+    pub shapes: Vec<ObstacleShape>,
+    pub hue: Option<f32>,  // light colour, None cycles through the crystal palette
+    pub weight: f32,  // relative spawn chance
+    pub min_speed: f32,  // level speed before it turns up at all
+    pub follow_up: Option<usize>  // a kind spawned right behind this one at the tightest fair gap
+} impl ObstacleKind {
+
+    // nothing touches the ground, so it's ducked under rather than jumped
+    pub fn overhead(&self) -> bool {
+        self.shapes.iter().all(|s| s.offset.z - s.collider.extent(Vec3::NEG_Z) > 0.0)
+    }
+
+    // how far the obstacle reaches along +x (or -x) from its centre
+    pub fn reach(&self, direction: f32) -> f32 {
+        let d = Vec3::X * direction.signum();
+        self.shapes.iter()
+            .map(|s| s.offset.x * direction.signum() + s.collider.extent(d))
+            .fold(0.0, f32::max)
+    }
+}
+
+fn hex(radius: f32, height: f32, x: f32) -> ObstacleShape {
+    ObstacleShape {
+        collider: Collider::HexPrism {radius, half_height: height / 2.0},
+        offset: Vec3::new(x, 0.0, height / 2.0)
+    }
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct ObstacleCatalogue {
+    pub kinds: Vec<ObstacleKind>
+} impl Default for ObstacleCatalogue {
+    fn default() -> Self {
+        Self {kinds: vec![
+            ObstacleKind {
+                name: "crystal",
+                shapes: vec![hex(OBSTACLE_RADIUS, OBSTACLE_HEIGHT, 0.0)],
+                hue: None,
+                weight: 6.0,
+                min_speed: 0.0,
+                follow_up: None
+            },
+            // too tall for a tapped jump, and too long a clearance for the dino's length at the
+            // starting speed
+            ObstacleKind {
+                name: "stalagmite",
+                shapes: vec![hex(0.45, 1.5, 0.0)],
+                hue: Some(190.0),
+                weight: 2.0,
+                min_speed: 6.5,
+                follow_up: None
+            },
+            ObstacleKind {
+                name: "cluster",
+                shapes: vec![hex(0.5, 0.8, -0.7), hex(0.55, 1.0, 0.0), hex(0.45, 0.7, 0.7)],
+                hue: Some(290.0),
+                weight: 2.0,
+                min_speed: 0.0,
+                follow_up: None
+            },
+            // a crystal with another one straight after it
+            ObstacleKind {
+                name: "double",
+                shapes: vec![hex(OBSTACLE_RADIUS, OBSTACLE_HEIGHT, 0.0)],
+                hue: None,
+                weight: 1.5,
+                min_speed: 0.0,
+                follow_up: Some(0)
+            },
+            // hangs from the ceiling, duck under it
+            ObstacleKind {
+                name: "stalactite",
+                shapes: vec![ObstacleShape {
+                    collider: Collider::HexPrism {radius: 0.45, half_height: STALACTITE_LENGTH / 2.0},
+                    offset: Vec3::new(0.0, 0.0, STALACTITE_BOTTOM + STALACTITE_LENGTH / 2.0)
+                }],
+                hue: Some(160.0),
+                weight: 1.5,
+                min_speed: 0.0,
+                follow_up: None
+            }
+        ]}
+    }
+} impl ObstacleCatalogue {
+
+    fn weight(&self, kind: usize, speed: f32) -> f32 {
+        let kind = &self.kinds[kind];
+        if speed < kind.min_speed {0.0} else {kind.weight.max(0.0)}
+    }
+
+    // `r` is a uniform random number in 0..1
+    pub fn pick(&self, r: f32, speed: f32) -> usize {
+        let total: f32 = (0..self.kinds.len()).map(|i| self.weight(i, speed)).sum();
+        let mut left = r * total;
+        for i in 0..self.kinds.len() {
+            let weight = self.weight(i, speed);
+            if left < weight {
+                return i;
+            };
+            left -= weight;
+        };
+        // r was 1.0 or nothing can spawn yet, fall back to the last kind that can
+        (0..self.kinds.len()).rev().find(|&i| self.weight(i, speed) > 0.0).unwrap_or(0)
+    }
+
+    // extra room between an obstacle and the next one when they're wider than the plain crystal
+    // the fair gap assumes
+    pub fn padding(&self, first: usize, next: usize) -> f32 {
+        let width = self.kinds[first].reach(1.0) + self.kinds[next].reach(-1.0);
+        (width - OBSTACLE_RADIUS * 2.0).max(0.0)
+    }
+}
@@ -0,0 +1,337 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use bevy::prelude::*;
+use crate::common::arg_value;
+use crate::dino_run::config::DinoRunConfig;
+use crate::dino_run::mechanics::JumpInput;
+use crate::dino_run::seed::RunSeed;
+use crate::dino_run::state::DinoRunState;
+use crate::dino_run::stats::RunStats;
+
+pub const RECORD_ARG: &str = "--record";
+pub const REPLAY_ARG: &str = "--replay";
+
+const REPLAY_MAGIC: &[u8; 4] = b"DRRP";
+const REPLAY_VERSION: u8 = 3;  // 2 added ducking, 3 the tuning, older files still play
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputKind {
+    Press,  // a press was latched for this step
+    Hold,  // the jump key went down
+    Release,  // the jump key came up
+    Duck,  // the duck key went down
+    Stand  // the duck key came up
+} impl InputKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            InputKind::Press => 0,
+            InputKind::Hold => 1,
+            InputKind::Release => 2,
+            InputKind::Duck => 3,
+            InputKind::Stand => 4
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(InputKind::Press),
+            1 => Some(InputKind::Hold),
+            2 => Some(InputKind::Release),
+            3 => Some(InputKind::Duck),
+            4 => Some(InputKind::Stand),
+            _ => None
+        }
+    }
+}
+
+// tick counts fixed steps from the start of the run
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InputEvent {
+    pub tick: u32,
+    pub kind: InputKind
+}
+
+// a run is its seed, the tuning it was played with and the jump inputs as player_jump_system saw
+// them, score and hits are kept so playback can tell whether it reproduced the run
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: Option<DinoRunConfig>,  // None for files from before version 3
+    pub end_tick: u32,
+    pub score: u32,
+    pub hits: u32,
+    pub events: Vec<InputEvent>
+} impl Replay {
+
+    // magic, version, seed, the tuning as RON text after its length, end tick, score, hits, event
+    // count, then per event the tick delta as a varint followed by the kind byte
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let config = self.config.as_ref()
+            .map(|config| ron::to_string(config).expect("the tuning always serializes"))
+            .unwrap_or_default();
+        let mut bytes = Vec::with_capacity(33 + config.len() + self.events.len() * 2);
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.push(REPLAY_VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(config.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(config.as_bytes());
+        bytes.extend_from_slice(&self.end_tick.to_le_bytes());
+        bytes.extend_from_slice(&self.score.to_le_bytes());
+        bytes.extend_from_slice(&self.hits.to_le_bytes());
+        bytes.extend_from_slice(&(self.events.len() as u32).to_le_bytes());
+        let mut last_tick = 0;
+        for event in &self.events {
+            write_varint(&mut bytes, event.tick - last_tick);
+            bytes.push(event.kind.to_byte());
+            last_tick = event.tick;
+        };
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = ByteReader {bytes, pos: 0};
+        if reader.take(4)? != REPLAY_MAGIC {
+            return Err("not a replay file".to_string());
+        };
+        let version = reader.take(1)?[0];
+        if !(1..=REPLAY_VERSION).contains(&version) {
+            return Err(format!("replay version {} is not {}", version, REPLAY_VERSION));
+        };
+        let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let config = if version >= 3 {
+            let length = reader.u32()? as usize;
+            let text = std::str::from_utf8(reader.take(length)?).map_err(|_| "tuning isn't text")?;
+            if text.is_empty() {
+                None
+            } else {
+                Some(DinoRunConfig::parse(text).map_err(|e| format!("tuning is {}", e))?)
+            }
+        } else {
+            None
+        };
+        let end_tick = reader.u32()?;
+        let score = reader.u32()?;
+        let hits = reader.u32()?;
+        let count = reader.u32()? as usize;
+        let mut events = Vec::with_capacity(count.min(bytes.len()));
+        let mut tick = 0u32;
+        for _ in 0..count {
+            tick = tick.checked_add(reader.varint()?).ok_or("tick overflow")?;
+            let kind = InputKind::from_byte(reader.take(1)?[0]).ok_or("unknown input kind")?;
+            events.push(InputEvent {tick, kind});
+        };
+        Ok(Self {seed, config, end_tick, score, hits, events})
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7F) | 0x80);
+        value >>= 7;
+    };
+    bytes.push(value as u8);
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+} impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos + n;
+        let slice = self.bytes.get(self.pos..end).ok_or("replay file is truncated")?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn varint(&mut self) -> Result<u32, String> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7F) as u32).checked_shl(shift).ok_or("bad varint")?;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            };
+        };
+        Err("bad varint".to_string())
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    path: PathBuf,
+    tick: u32,
+    held: bool,
+    duck: bool,
+    events: Vec<InputEvent>
+} impl ReplayRecorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self {path, tick: 0, held: false, duck: false, events: Vec::new()}
+    }
+}
+
+#[derive(Resource)]
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    tick: u32,
+    cursor: usize
+} impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {replay, tick: 0, cursor: 0}
+    }
+
+    // whether the run played back ended where the recording did
+    pub fn matches(&self, stats: &RunStats) -> bool {
+        (stats.score(), stats.hits) == (self.replay.score, self.replay.hits) && self.tick == self.replay.end_tick
+    }
+}
+
+// `--record run.dinoreplay` saves every finished run, `--replay run.dinoreplay` plays one back
+#[derive(Resource, Default)]
+pub struct ReplaySettings {
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>
+} impl ReplaySettings {
+    pub fn from_env() -> Self {
+        Self {
+            record: arg_value(RECORD_ARG).map(PathBuf::from),
+            replay: arg_value(REPLAY_ARG).map(PathBuf::from)
+        }
+    }
+}
+
+pub fn setup_replay(
+    mut commands: Commands,
+    settings: Res<ReplaySettings>,
+    mut run_seed: ResMut<RunSeed>,
+    mut config: ResMut<DinoRunConfig>
+) {
+    if let Some(path) = &settings.replay {
+        match Replay::load(path) {
+            Ok(replay) => {
+                println!("Playing back {} (seed {})", path.display(), replay.seed);
+                *run_seed = RunSeed::fixed(replay.seed);
+                if let Some(recorded) = &replay.config {
+                    config.set_if_neq(recorded.clone());
+                } else {
+                    eprintln!("{} doesn't say what tuning it was played with, it may not match", path.display());
+                };
+                commands.insert_resource(ReplayPlayer::new(replay));
+            },
+            Err(e) => eprintln!("Couldn't load replay {}: {}", path.display(), e)
+        };
+    } else if let Some(path) = &settings.record {
+        commands.insert_resource(ReplayRecorder::new(path.clone()));
+    };
+}
+
+pub fn record_input(
+    mut recorder: ResMut<ReplayRecorder>,
+    jump_input: Res<JumpInput>
+) {
+    let tick = recorder.tick;
+    if jump_input.held != recorder.held {
+        let kind = if jump_input.held {InputKind::Hold} else {InputKind::Release};
+        recorder.events.push(InputEvent {tick, kind});
+        recorder.held = jump_input.held;
+    };
+    if jump_input.duck != recorder.duck {
+        let kind = if jump_input.duck {InputKind::Duck} else {InputKind::Stand};
+        recorder.events.push(InputEvent {tick, kind});
+        recorder.duck = jump_input.duck;
+    };
+    if jump_input.pressed {
+        recorder.events.push(InputEvent {tick, kind: InputKind::Press});
+    };
+    recorder.tick += 1;
+}
+
+pub fn play_back_input(
+    mut player: ResMut<ReplayPlayer>,
+    mut jump_input: ResMut<JumpInput>
+) {
+    let tick = player.tick;
+    while let Some(event) = player.replay.events.get(player.cursor).copied() {
+        if event.tick > tick {
+            break;
+        };
+        match event.kind {
+            InputKind::Press => jump_input.pressed = true,
+            InputKind::Hold => jump_input.held = true,
+            InputKind::Release => jump_input.held = false,
+            InputKind::Duck => jump_input.duck = true,
+            InputKind::Stand => jump_input.duck = false
+        };
+        player.cursor += 1;
+    };
+    player.tick += 1;
+}
+
+pub fn start_replay(
+    mut next_state: ResMut<NextState<DinoRunState>>
+) {
+    next_state.set(DinoRunState::Running);
+}
+
+pub fn save_recording(
+    recorder: Res<ReplayRecorder>,
+    stats: Res<RunStats>,
+    run_seed: Res<RunSeed>,
+    config: Res<DinoRunConfig>
+) {
+    let replay = Replay {
+        seed: run_seed.seed,
+        config: Some(config.clone()),
+        end_tick: recorder.tick,
+        score: stats.score(),
+        hits: stats.hits,
+        events: recorder.events.clone()
+    };
+    match replay.save(&recorder.path) {
+        Ok(()) => println!("Saved replay to {}", recorder.path.display()),
+        Err(e) => eprintln!("Couldn't save replay {}: {}", recorder.path.display(), e)
+    };
+}
+
+pub fn check_playback(
+    player: Res<ReplayPlayer>,
+    stats: Res<RunStats>
+) {
+    let replay = &player.replay;
+    if player.matches(&stats) {
+        println!("Replay matched: score {}, {} hits after {} steps", replay.score, replay.hits, replay.end_tick);
+    } else {
+        println!(
+            "Replay diverged: expected score {}, {} hits after {} steps, got score {}, {} hits after {}",
+            replay.score, replay.hits, replay.end_tick, stats.score(), stats.hits, player.tick
+        );
+    };
+}
+
+pub fn reset_replay(
+    recorder: Option<ResMut<ReplayRecorder>>,
+    player: Option<ResMut<ReplayPlayer>>
+) {
+    if let Some(mut recorder) = recorder {
+        recorder.tick = 0;
+        recorder.held = false;
+        recorder.duck = false;
+        recorder.events.clear();
+    };
+    if let Some(mut player) = player {
+        player.tick = 0;
+        player.cursor = 0;
+    };
+}
@@ -0,0 +1,61 @@
+use std::env;
+use bevy::prelude::*;
+use fastrand::Rng;
+use crate::common::arg_value;
+
+pub const SEED_ARG: &str = "--seed";
+pub const SEED_ENV: &str = "DINO_RUN_SEED";
+
+// xor'd into the run seed so the cave decoration doesn't draw the same numbers as the obstacles
+const CRYSTAL_SALT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct RunSeed {
+    pub seed: u64,
+    pub fixed: bool  // picked by the player, so every restart replays the same run
+} impl Default for RunSeed {
+    fn default() -> Self {
+        Self {seed: fastrand::u64(..), fixed: false}
+    }
+} impl RunSeed {
+
+    pub fn fixed(seed: u64) -> Self {
+        Self {seed, fixed: true}
+    }
+
+    // `--seed 1234` or `--seed=1234` on the command line wins over the DINO_RUN_SEED variable
+    pub fn from_env() -> Self {
+        Self::from_sources(arg_value(SEED_ARG), env::var(SEED_ENV).ok())
+    }
+
+    pub fn from_sources(arg: Option<String>, var: Option<String>) -> Self {
+        match arg.or(var).map(|t| t.trim().parse::<u64>()) {
+            Some(Ok(seed)) => Self::fixed(seed),
+            Some(Err(e)) => {
+                eprintln!("Ignoring bad seed ({}), picking a random one", e);
+                Self::default()
+            },
+            None => Self::default()
+        }
+    }
+
+    pub fn reroll(&mut self) {
+        if !self.fixed {
+            self.seed = fastrand::u64(..);
+        };
+    }
+
+    pub fn obstacle_rng(&self) -> Rng {
+        Rng::with_seed(self.seed)
+    }
+
+    pub fn crystal_rng(&self) -> Rng {
+        Rng::with_seed(self.seed ^ CRYSTAL_SALT)
+    }
+}
+
+pub fn reroll_seed(
+    mut run_seed: ResMut<RunSeed>
+) {
+    run_seed.reroll();
+}
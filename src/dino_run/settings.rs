@@ -0,0 +1,148 @@
+use std::path::PathBuf;
+use bevy::prelude::*;
+use bevy::core_pipeline::bloom::Bloom;
+use bevy::window::{MonitorSelection, PresentMode, PrimaryWindow, WindowMode};
+use serde::{Deserialize, Serialize};
+use crate::common::{Mixer, load_versioned, save_ron};
+
+const SETTINGS_VERSION: u32 = 1;
+const SETTINGS_FILE: &str = "dino_run_settings.ron";
+
+// the player's options, as opposed to DinoRunConfig's game tuning
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32,  // 0 to 1
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub bloom: bool,
+    pub msaa: bool,
+    pub shadows: bool,  // for the crystal and obstacle point lights, by far the heaviest part of a frame
+    pub hdr: bool,
+    pub fullscreen: bool,
+    pub vsync: bool
+} impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            bloom: true,
+            msaa: true,
+            shadows: true,
+            hdr: true,
+            fullscreen: false,
+            vsync: true
+        }
+    }
+} impl Settings {
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("art_fight").join(SETTINGS_FILE))
+    }
+
+    pub fn load(path: &Option<PathBuf>) -> Self {
+        let Some(path) = path else {
+            eprintln!("No config directory found, settings won't be saved");
+            return Self::default();
+        };
+        match load_versioned::<SettingsFile>(path, SETTINGS_VERSION, "settings") {
+            Ok(Some(file)) => file.settings.clamped(),
+            _ => Self::default()
+        }
+    }
+
+    fn clamped(mut self) -> Self {
+        for volume in [&mut self.master_volume, &mut self.music_volume, &mut self.sfx_volume] {
+            *volume = if volume.is_finite() {volume.clamp(0.0, 1.0)} else {1.0};
+        };
+        self
+    }
+
+    pub fn save(&self, path: &Option<PathBuf>) -> Result<(), String> {
+        let Some(path) = path else {
+            return Ok(());
+        };
+        save_ron(path, &SettingsFile {version: SETTINGS_VERSION, settings: self.clone()})
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SettingsFile {
+    version: u32,
+    settings: Settings
+}
+
+#[derive(Resource)]
+pub struct SettingsPath(pub Option<PathBuf>);
+
+pub fn load_settings(
+    mut commands: Commands
+) {
+    let path = Settings::default_path();
+    commands.insert_resource(Settings::load(&path));
+    commands.insert_resource(SettingsPath(path));
+}
+
+pub fn save_settings(
+    settings: Res<Settings>,
+    path: Res<SettingsPath>
+) {
+    if let Err(e) = settings.save(&path.0) {
+        eprintln!("Couldn't save settings: {}", e);
+    };
+}
+
+pub fn apply_audio_settings(
+    settings: Res<Settings>,
+    mut mixer: ResMut<Mixer>
+) {
+    mixer.master = settings.master_volume;
+    mixer.music = settings.music_volume;
+    mixer.sfx = settings.sfx_volume;
+}
+
+pub fn apply_camera_settings(
+    settings: Res<Settings>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Camera), With<Camera3d>>
+) {
+    for (entity, mut camera) in &mut query {
+        camera.hdr = settings.hdr;
+        let mut entity = commands.entity(entity);
+        if settings.bloom {
+            entity.insert(Bloom::OLD_SCHOOL);
+        } else {
+            entity.remove::<Bloom>();
+        };
+        entity.insert(if settings.msaa {Msaa::Sample4} else {Msaa::Off});
+    };
+}
+
+// lights spawn with shadows on, this turns them off as they appear if the player doesn't want them
+pub fn apply_shadow_settings(
+    settings: Res<Settings>,
+    mut query: Query<&mut PointLight>
+) {
+    let all = settings.is_changed();
+    for mut light in &mut query {
+        if all || light.is_added() {
+            light.shadows_enabled = settings.shadows;
+        };
+    };
+}
+
+pub fn apply_window_settings(
+    settings: Res<Settings>,
+    mut query: Query<&mut Window, With<PrimaryWindow>>
+) {
+    let Ok(mut window) = query.single_mut() else {
+        return;
+    };
+    window.mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+    } else {
+        WindowMode::Windowed
+    };
+    window.present_mode = if settings.vsync {PresentMode::AutoVsync} else {PresentMode::AutoNoVsync};
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::window::WindowFocused;
+use crate::dino_run::mechanics::PlayerDied;
+use crate::dino_run::input::Action;
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DinoRunState {
+    #[default]
+    Title,
+    Running,
+    Paused,
+    GameOver
+}
+
+// everything that puts the run back to its start, run on leaving GameOver or by a restart mid-run
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResetRun;
+
+pub fn reset_run(
+    world: &mut World
+) {
+    world.run_schedule(ResetRun);
+}
+
+const GAME_OVER_GRACE_SECS: f32 = 0.75;
+
+// how long the game over screen ignores jump, so a player still mashing it when they die gets to see
+// their score, restart works straight away
+#[derive(Resource)]
+pub struct GameOverGrace(Timer);
+impl Default for GameOverGrace {
+    fn default() -> Self {
+        Self(Timer::from_seconds(GAME_OVER_GRACE_SECS, TimerMode::Once))
+    }
+}
+
+pub fn start_game_over_grace(
+    mut grace: ResMut<GameOverGrace>
+) {
+    grace.0.reset();
+}
+
+pub fn start_run(
+    actions: Res<ButtonInput<Action>>,
+    state: Res<State<DinoRunState>>,
+    time: Res<Time>,
+    mut grace: ResMut<GameOverGrace>,
+    mut next_state: ResMut<NextState<DinoRunState>>
+) {
+    let waiting = *state.get() == DinoRunState::GameOver && !grace.0.tick(time.delta()).finished();
+    if actions.just_pressed(Action::Restart) || (actions.just_pressed(Action::Jump) && !waiting) {
+        next_state.set(DinoRunState::Running);
+    };
+}
+
+// the run starts over straight away, without a game over and so without a high score
+pub fn restart_run(
+    actions: Res<ButtonInput<Action>>,
+    state: Res<State<DinoRunState>>,
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<DinoRunState>>
+) {
+    if !actions.just_pressed(Action::Restart) {
+        return;
+    };
+    commands.run_schedule(ResetRun);
+    if *state.get() == DinoRunState::Paused {
+        next_state.set(DinoRunState::Running);
+    };
+}
+
+pub fn toggle_pause(
+    actions: Res<ButtonInput<Action>>,
+    state: Res<State<DinoRunState>>,
+    mut next_state: ResMut<NextState<DinoRunState>>
+) {
+    if !actions.just_pressed(Action::Pause) {
+        return;
+    };
+    match state.get() {
+        DinoRunState::Running => next_state.set(DinoRunState::Paused),
+        DinoRunState::Paused => next_state.set(DinoRunState::Running),
+        _ => {}
+    };
+}
+
+// clicking away from the game shouldn't cost a life
+pub fn pause_on_focus_loss(
+    mut event_reader: EventReader<WindowFocused>,
+    state: Res<State<DinoRunState>>,
+    mut next_state: ResMut<NextState<DinoRunState>>
+) {
+    let lost_focus = event_reader.read().last().is_some_and(|event| !event.focused);
+    if lost_focus && *state.get() == DinoRunState::Running {
+        next_state.set(DinoRunState::Paused);
+    };
+}
+
+// virtual time stands still, so everything driven by Time (the fixed steps, animation, popups) freezes
+pub fn pause_time(
+    mut time: ResMut<Time<Virtual>>
+) {
+    time.pause();
+}
+
+pub fn resume_time(
+    mut time: ResMut<Time<Virtual>>
+) {
+    time.unpause();
+}
+
+pub fn end_run(
+    mut event_reader: EventReader<PlayerDied>,
+    mut next_state: ResMut<NextState<DinoRunState>>
+) {
+    for _ in event_reader.read() {
+        next_state.set(DinoRunState::GameOver);
+    };
+}
@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+use crate::dino_run::mechanics::{LevelSpeed, PlayerHurt, PlayerScores};
+
+#[derive(Resource, Default, Clone, Debug)]
+pub struct RunStats {
+    pub cleared: u32,
+    pub streak: u32,
+    pub best_streak: u32,
+    pub hits: u32,
+    pub distance: f32,
+    pub elapsed: f32
+} impl RunStats {
+
+    pub fn score(&self) -> u32 {
+        self.cleared
+    }
+
+    fn clear_obstacle(&mut self) {
+        self.cleared += 1;
+        self.streak += 1;
+        self.best_streak = self.best_streak.max(self.streak);
+    }
+
+    fn take_hit(&mut self) {
+        self.hits += 1;
+        self.streak = 0;
+    }
+}
+
+pub fn track_run_stats(
+    mut stats: ResMut<RunStats>,
+    mut score_reader: EventReader<PlayerScores>,
+    mut hurt_reader: EventReader<PlayerHurt>,
+    time: Res<Time>,
+    speed: Res<LevelSpeed>
+) {
+    let dt = time.delta_secs();
+    stats.elapsed += dt;
+    stats.distance += speed.f32 * dt;
+    for _ in score_reader.read() {
+        stats.clear_obstacle();
+    };
+    for _ in hurt_reader.read() {
+        stats.take_hit();
+    };
+}
+
+pub fn reset_run_stats(
+    mut stats: ResMut<RunStats>
+) {
+    *stats = RunStats::default();
+}
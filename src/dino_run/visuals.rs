@@ -0,0 +1,233 @@
+use std::f32::consts::FRAC_PI_2;
+use bevy::prelude::*;
+use bevy::pbr::{NotShadowCaster, NotShadowReceiver};
+use bevy::render::view::NoFrustumCulling;
+use crate::common::MaterialWizard;
+use crate::event_exists;
+use crate::dino_run::mechanics::{HurtCounters, Obstacle, ObstaclePart, Player, PlayerScores};
+use crate::dino_run::obstacles::ObstacleCatalogue;
+use crate::dino_run::character::{spawn_legs, animate_legs, spawn_body, animate_tail, spawn_neck_and_head};
+use crate::dino_run::environment::{
+    spawn_cave_tunnel, insert_crystal_stuff, spawn_crystals, update_lights, reset_crystals
+};
+use crate::dino_run::hud::{
+    spawn_hud, update_hud, update_banner, spawn_score_popups, animate_score_popups, hurt_vignette,
+    clear_score_popups
+};
+use crate::dino_run::seed::reroll_seed;
+use crate::dino_run::collision::{Collider, ShowColliders, toggle_collider_gizmos, draw_collider_gizmos};
+use crate::dino_run::menu::{
+    MenuPage, reset_menu_page, spawn_pause_menu, despawn_pause_menu, highlight_buttons, pause_menu_buttons,
+    settings_buttons, update_settings_labels
+};
+use crate::dino_run::settings::{
+    Settings, apply_camera_settings, apply_shadow_settings, apply_window_settings
+};
+use crate::dino_run::state::{DinoRunState, ResetRun};
+
+// meshes, lights, animation and the HUD, layered over DinoRunSimPlugin's entities
+pub struct DinoRunVisualsPlugin;
+impl Plugin for DinoRunVisualsPlugin {
+    fn build(&self, app: &mut App) {
+        let running = in_state(DinoRunState::Running);
+        app.init_resource::<MenuPage>();
+        app.add_systems(OnEnter(DinoRunState::Paused), (reset_menu_page, spawn_pause_menu).chain());
+        app.add_systems(OnExit(DinoRunState::Paused), despawn_pause_menu);
+        app.add_systems(Update, (highlight_buttons, pause_menu_buttons, settings_buttons)
+            .run_if(in_state(DinoRunState::Paused)));
+        app.add_systems(Update, spawn_pause_menu.run_if(
+            in_state(DinoRunState::Paused).and(resource_changed::<MenuPage>)));
+        app.add_systems(Update, update_settings_labels.run_if(resource_changed::<Settings>));
+        app.add_systems(Update, apply_camera_settings.run_if(resource_changed::<Settings>));
+        app.add_systems(Update, apply_shadow_settings);
+        app.add_systems(Update, apply_window_settings.run_if(resource_changed::<Settings>));
+        app.add_systems(Startup, insert_obstacle_assets);
+        app.add_systems(Update, (dress_player, dress_obstacles));
+        app.add_systems(Update, (interpolate_player, interpolate_obstacles).run_if(running.clone()));
+        app.add_systems(Update, hurt_flicker);
+        app.add_systems(Update, (animate_legs, animate_tail).run_if(running.clone()));
+        app.add_systems(Startup, spawn_cave_tunnel);
+        app.add_systems(Startup, insert_crystal_stuff);
+        app.add_systems(Update, (spawn_crystals, update_lights).chain().run_if(running));
+        app.add_systems(ResetRun, reset_crystals.after(reroll_seed));
+        app.add_systems(Startup, spawn_hud);
+        app.add_systems(Update, update_hud);
+        app.add_systems(Update, update_banner.run_if(state_changed::<DinoRunState>));
+        app.add_systems(PostUpdate, spawn_score_popups.run_if(event_exists!(PlayerScores)));
+        app.add_systems(Update, (animate_score_popups, hurt_vignette));
+        app.add_systems(ResetRun, clear_score_popups);
+        app.init_resource::<ShowColliders>();
+        app.add_systems(Update, toggle_collider_gizmos);
+        app.add_systems(Update, draw_collider_gizmos.run_if(resource_equals(ShowColliders(true))));
+    }
+}
+
+fn dress_player(
+    query: Query<Entity, Added<Player>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for player in query {
+        commands.entity(player).insert(Visibility::Visible);
+        let hip = spawn_legs(
+            player,
+            &mut commands,
+            &mut meshes,
+            &mut materials
+        );
+        let body = spawn_body(
+            hip,
+            &mut commands,
+            &mut meshes,
+            &mut materials
+        );
+        spawn_neck_and_head(
+            body,
+            &mut commands,
+            &mut meshes,
+            &mut materials
+        );
+    };
+}
+
+fn interpolate_player(
+    mut query: Query<(&mut Transform, &Player)>,
+    fixed_time: Res<Time<Fixed>>
+) {
+    let alpha = fixed_time.overstep_fraction();
+    for (mut t, p) in &mut query {
+        t.translation.z = p.previous_z + (p.z - p.previous_z) * alpha;
+    };
+}
+
+fn hurt_flicker(
+    mut vis_query: Query<&mut Visibility, With<Player>>,
+    hurt_counters: Res<HurtCounters>
+) {
+    let Ok(mut v) = vis_query.single_mut() else {
+        return;
+    };
+    let is_visible = matches!(*v, Visibility::Visible);
+    if is_visible != hurt_counters.should_show {
+        v.toggle_visible_hidden();
+    };
+}
+
+#[derive(Resource)]
+struct ObstacleAssets {
+    meshes: Vec<Vec<Handle<Mesh>>>,  // by kind, then shape
+    wizard: MaterialWizard,
+    hues: Vec<f32>
+}
+
+// the mesh is just the collider made solid, so what you see is what you hit
+fn collider_mesh(collider: &Collider) -> Mesh {
+    match *collider {
+        Collider::Cuboid {half_size} => Cuboid::from_size(half_size * 2.0).into(),
+        Collider::Capsule {half_length, radius} => Mesh::from(Capsule3d::new(radius, half_length * 2.0))
+            .rotated_by(Quat::from_rotation_x(FRAC_PI_2)),
+        Collider::HexPrism {radius, half_height} => {
+            Extrusion::new(RegularPolygon::new(radius, 6), half_height * 2.0).into()
+        }
+    }
+}
+
+const COLOR_COUNT: usize = 16;
+
+fn insert_obstacle_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    catalogue: Res<ObstacleCatalogue>
+) {
+    let wizard = {
+        let saturation = 1.0;
+        let lightness = 0.5;
+        let alpha = 0.5;
+        let color_count = COLOR_COUNT;
+        let crystal_mat = StandardMaterial {
+            base_color: Color::WHITE.with_alpha(alpha),
+            perceptual_roughness: 0.2,
+            reflectance: 0.6,
+            diffuse_transmission: 0.3,
+            ..default()
+        };
+        MaterialWizard::new(
+            &mut materials, crystal_mat, saturation,
+            lightness, alpha, color_count, 0.5, false,
+        )
+    };
+    let hues: Vec<f32> = {
+        let normals = MaterialWizard::generate_normal_hue_vec(COLOR_COUNT);
+        let mut hues: Vec<f32> = Vec::with_capacity(COLOR_COUNT);
+        for normal in normals.iter().take(COLOR_COUNT) {
+            hues.push(normal * 360.0);
+        };
+        hues
+    };
+    let kind_meshes = catalogue.kinds.iter()
+        .map(|kind| kind.shapes.iter().map(|s| meshes.add(collider_mesh(&s.collider))).collect())
+        .collect();
+    let obs_assets = ObstacleAssets {
+        meshes: kind_meshes,
+        wizard,
+        hues
+    };
+    commands.insert_resource(obs_assets);
+}
+
+fn dress_obstacles(
+    obstacle_query: Query<(Entity, &Obstacle), Added<Obstacle>>,
+    part_query: Query<(Entity, &ObstaclePart, &ChildOf), Added<ObstaclePart>>,
+    parent_query: Query<&Obstacle>,
+    assets: Res<ObstacleAssets>,
+    catalogue: Res<ObstacleCatalogue>,
+    mut commands: Commands
+) {
+    for (entity, _) in obstacle_query {
+        commands.entity(entity).try_insert(Visibility::Visible);
+    };
+    for (entity, part, child_of) in part_query {
+        let Ok(obstacle) = parent_query.get(child_of.parent()) else {
+            continue;
+        };
+        let i = obstacle.count as usize % COLOR_COUNT;
+        commands.entity(entity).try_insert(
+            (
+                Mesh3d(assets.meshes[obstacle.kind][part.index].clone()),
+                MeshMaterial3d(assets.wizard.get_index(i)),
+                Visibility::Inherited,
+                NotShadowCaster,
+                NotShadowReceiver,
+                NoFrustumCulling
+            )
+        );
+        // one light per obstacle, on its first crystal
+        if part.index != 0 {
+            continue;
+        };
+        let hue = catalogue.kinds[obstacle.kind].hue.unwrap_or(assets.hues[i]);
+        commands.entity(entity).try_insert(
+            PointLight {
+                color: Color::hsl(hue, 1.0, 0.6),
+                intensity: 32000.0,
+                range: 10.0,
+                shadows_enabled: true,
+                radius: 0.5,
+                shadow_map_near_z: 1.0,
+                ..default()
+            }
+        );
+    };
+}
+
+fn interpolate_obstacles(
+    mut query: Query<(&mut Transform, &Obstacle)>,
+    fixed_time: Res<Time<Fixed>>
+) {
+    let alpha = fixed_time.overstep_fraction();
+    for (mut t, obstacle) in &mut query {
+        t.translation.x = obstacle.previous_x + (obstacle.x - obstacle.previous_x) * alpha;
+    };
+}
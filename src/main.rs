@@ -1,19 +1,33 @@
 use bevy::prelude::*;
-use rodio::OutputStream;
-
-mod common;
-use common::AudioSystem;
-
-mod dino_run;
-use dino_run::mechanics::DinoRunPlugin;
+use art_fight::common::{AudioSystem, arg_value};
+use art_fight::dino_run::mechanics::DinoRunPlugin;
+use art_fight::dino_run::seed::RunSeed;
+use art_fight::dino_run::replay::ReplaySettings;
+use art_fight::dino_run::collision::ShowColliders;
+use art_fight::dino_run::config::ConfigFile;
+use art_fight::dino_run::headless::{HEADLESS_ARG, run_headless};
 
 // MAIN
 fn main() {
+    if let Some(runs) = arg_value(HEADLESS_ARG) {
+        match runs.parse::<u32>() {
+            Ok(runs) => run_headless(runs, RunSeed::from_env()),
+            Err(e) => eprintln!("Bad run count for {} ({})", HEADLESS_ARG, e)
+        };
+        return;
+    };
     println!("hello world!");
-    let (_stream, handle) = OutputStream::try_default().unwrap();
+    let (_stream, audio_system) = AudioSystem::from_env();
+    let mut config_file = ConfigFile::from_env();
+    let config = config_file.load();
     App::new()
         .add_plugins(DefaultPlugins)
-        .insert_resource(AudioSystem::new(handle))
+        .insert_resource(audio_system)
+        .insert_resource(RunSeed::from_env())
+        .insert_resource(ReplaySettings::from_env())
+        .insert_resource(ShowColliders::from_env())
+        .insert_resource(config)
+        .insert_resource(config_file)
         .add_plugins(DinoRunPlugin)
         .run();
-}
\ No newline at end of file
+}
@@ -0,0 +1,168 @@
+use rodio::{Decoder, Source};
+use std::io::Cursor;
+use bevy::prelude::*;
+use art_fight::common::{AudioSystem, Mixer, MusicStem, SoundBank, SoundParams, panned};
+use art_fight::dino_run::audio::obstacle_pan;
+use art_fight::dino_run::headless::{autopilot_app, run_to_game_over};
+use art_fight::dino_run::mechanics::{PlayerHurt, PlayerScores};
+use art_fight::dino_run::seed::RunSeed;
+
+const JUMP: &[u8] = include_bytes!("../src/dino_run/sound_files/jump.wav");
+const TRACK: &[u8] = include_bytes!("../src/dino_run/sound_files/crystal.wav");
+const STEMS: [MusicStem; 2] = [
+    MusicStem {data: TRACK, speed: 1.0},
+    MusicStem {data: TRACK, speed: 2.0}
+];
+
+fn assert_volumes(audio: &AudioSystem, expected: &[f32]) {
+    let volumes = audio.music_volumes();
+    assert_eq!(volumes.len(), expected.len());
+    for (volume, expected) in volumes.iter().zip(expected) {
+        assert!((volume - expected).abs() < 1e-4, "{:?} is not {:?}", volumes, expected);
+    };
+}
+
+#[test]
+fn voices_are_capped() {
+    let mut audio = AudioSystem::silent();
+    let jump = audio.load_sound(JUMP);
+    audio.set_mixer(&Mixer {max_voices: 4, ..Mixer::default()});
+    for _ in 0..10 {
+        audio.play(jump);
+    };
+    assert_eq!(audio.voice_count(), 4);
+}
+
+#[test]
+fn muted_mixer_plays_nothing() {
+    let mut audio = AudioSystem::silent();
+    let jump = audio.load_sound(JUMP);
+    audio.play_music(&STEMS, &[1.0, 1.0]);
+    audio.set_mixer(&Mixer {muted: true, ..Mixer::default()});
+    audio.play_with(jump, SoundParams {volume: 0.5, pitch: 1.5, pan: Some(0.5)});
+    assert_eq!(audio.voice_count(), 0);
+    assert_volumes(&audio, &[0.0, 0.0]);
+}
+
+#[test]
+fn bus_gains_multiply() {
+    let mut audio = AudioSystem::silent();
+    audio.play_music(&STEMS, &[1.0, 0.5]);
+    audio.set_mixer(&Mixer {master: 0.5, music: 0.5, ..Mixer::default()});
+    assert_volumes(&audio, &[0.25, 0.125]);
+}
+
+#[test]
+fn cached_sound_matches_the_file() {
+    let mut bank = SoundBank::default();
+    let jump = bank.load(JUMP);
+    let decoded = Decoder::new(Cursor::new(JUMP)).unwrap();
+    let (channels, sample_rate) = (decoded.channels(), decoded.sample_rate());
+    let samples: Vec<f32> = decoded.convert_samples().collect();
+    for _ in 0..2 {
+        let source = bank.source(jump);
+        assert_eq!((source.channels(), source.sample_rate()), (channels, sample_rate));
+        assert_eq!(source.collect::<Vec<f32>>(), samples);
+    };
+}
+
+#[test]
+fn stems_crossfade_at_a_steady_rate() {
+    let mut audio = AudioSystem::silent();
+    audio.play_music(&STEMS, &[1.0, 0.0]);
+    audio.set_music_levels(&[0.0, 1.0], 2.0);
+    audio.update_music(0.5);
+    assert_volumes(&audio, &[0.75, 0.25]);
+    // asking again each frame doesn't slow it down
+    audio.set_music_levels(&[0.0, 1.0], 2.0);
+    audio.update_music(1.0);
+    assert_volumes(&audio, &[0.25, 0.75]);
+    audio.update_music(1.0);
+    assert_volumes(&audio, &[0.0, 1.0]);
+}
+
+#[test]
+fn ducking_recovers() {
+    let mut audio = AudioSystem::silent();
+    audio.play_music(&STEMS, &[1.0, 0.5]);
+    audio.duck_music(0.6, 0.8);
+    assert_volumes(&audio, &[0.4, 0.2]);
+    audio.update_music(0.4);
+    assert_volumes(&audio, &[0.7, 0.35]);
+    audio.update_music(1.0);
+    assert_volumes(&audio, &[1.0, 0.5]);
+}
+
+#[test]
+fn music_fades_out() {
+    let mut audio = AudioSystem::silent();
+    audio.play_music(&STEMS, &[1.0, 0.5]);
+    audio.fade_out_music(1.0);
+    audio.update_music(0.5);
+    assert_volumes(&audio, &[0.5, 0.0]);
+    audio.update_music(0.5);
+    assert_volumes(&audio, &[0.0, 0.0]);
+}
+
+#[test]
+fn panned_sounds_lean_to_their_side() {
+    let mut bank = SoundBank::default();
+    let jump = bank.load(JUMP);
+    // summed loudness of the left and right channels
+    let sides = |pan: f32| {
+        let source = panned(bank.source(jump), pan);
+        assert_eq!(source.channels(), 2);
+        let samples: Vec<f32> = source.collect();
+        samples.chunks(2).fold((0.0, 0.0), |(left, right), frame| (left + frame[0].abs(), right + frame[1].abs()))
+    };
+    let (left, right) = sides(0.0);
+    assert!((left - right).abs() < 1e-3 * left, "{} {}", left, right);
+    let (left, right) = sides(0.8);
+    assert!(right > 1.5 * left, "{} {}", left, right);
+    let (left, right) = sides(-0.8);
+    assert!(left > 1.5 * right, "{} {}", left, right);
+}
+
+#[test]
+fn sting_can_be_cut_off() {
+    let mut audio = AudioSystem::silent();
+    audio.play_music(&STEMS, &[1.0, 0.0]);
+    audio.play_sting(MusicStem {data: TRACK, speed: 0.5}, 1.0);
+    assert!(audio.sting_playing());
+    audio.stop_sting();
+    assert!(!audio.sting_playing());
+    assert_volumes(&audio, &[1.0, 0.0]);
+}
+
+#[derive(Resource, Default)]
+struct Pans {
+    scores: Vec<f32>,
+    hurts: Vec<f32>
+}
+
+fn record_pans(
+    mut pans: ResMut<Pans>,
+    mut scores: EventReader<PlayerScores>,
+    mut hurts: EventReader<PlayerHurt>
+) {
+    pans.scores.extend(scores.read().map(|score| obstacle_pan(score.x)));
+    pans.hurts.extend(hurts.read().map(|hurt| obstacle_pan(hurt.x)));
+}
+
+// from where the events really fire: clears lean a little left, behind the dino, and hits a little
+// right, in front of it, neither far off centre
+#[test]
+fn clears_and_hits_pan_near_the_middle() {
+    let mut app = autopilot_app(RunSeed::fixed(3), 0.3);
+    app.init_resource::<Pans>();
+    app.add_systems(Update, record_pans);
+    run_to_game_over(&mut app);
+    let pans = app.world_mut().remove_resource::<Pans>().unwrap();
+    assert!(!pans.scores.is_empty() && !pans.hurts.is_empty());
+    for pan in pans.scores {
+        assert!((-0.3..0.0).contains(&pan), "clear panned to {}", pan);
+    };
+    for pan in pans.hurts {
+        assert!((0.0..0.3).contains(&pan), "hit panned to {}", pan);
+    };
+}
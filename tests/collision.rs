@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_6;
+use art_fight::dino_run::collision::{Collider, intersects};
+
+const HEX: Collider = Collider::HexPrism {radius: 0.75, half_height: 0.5};
+const CUBE: Collider = Collider::Cuboid {half_size: Vec3::splat(0.25)};
+
+fn at(x: f32, y: f32, z: f32) -> Isometry3d {
+    Isometry3d::from_translation(Vec3::new(x, y, z))
+}
+
+#[test]
+fn hex_prism_corners_and_flats() {
+    // the first corner is on +y, so along x the hex only reaches its flat at r * cos(30)
+    let flat = 0.75 * FRAC_PI_6.cos();
+    assert!(intersects(&HEX, at(0.0, 0.0, 0.0), &CUBE, at(flat + 0.2, 0.0, 0.0)));
+    assert!(!intersects(&HEX, at(0.0, 0.0, 0.0), &CUBE, at(flat + 0.3, 0.0, 0.0)));
+    assert!(intersects(&HEX, at(0.0, 0.0, 0.0), &CUBE, at(0.0, 0.75 + 0.2, 0.0)));
+    // turned a sixth of the way round, a corner points along x instead
+    let turned = Isometry3d::new(Vec3::ZERO, Quat::from_rotation_z(FRAC_PI_6));
+    assert!(intersects(&HEX, turned, &CUBE, at(flat + 0.3, 0.0, 0.0)));
+}
+
+#[test]
+fn capsule_clears_the_top_of_a_prism() {
+    let leg = Collider::Capsule {half_length: 0.4, radius: 0.1};
+    assert!(intersects(&leg, at(0.0, 0.0, 1.45), &HEX, at(0.0, 0.0, 0.5)));
+    assert!(!intersects(&leg, at(0.0, 0.0, 1.55), &HEX, at(0.0, 0.0, 0.5)));
+}
+
+#[test]
+fn rotated_cuboids() {
+    let plank = Collider::Cuboid {half_size: Vec3::new(1.0, 0.1, 0.1)};
+    let diagonal = Isometry3d::new(Vec3::ZERO, Quat::from_rotation_z(FRAC_PI_6 * 1.5));
+    assert!(intersects(&plank, diagonal, &CUBE, at(0.6, 0.6, 0.0)));
+    assert!(!intersects(&plank, diagonal, &CUBE, at(0.9, 0.0, 0.0)));
+    assert!(!intersects(&plank, at(0.0, 0.0, 0.0), &CUBE, at(0.0, 0.0, 0.4)));
+}
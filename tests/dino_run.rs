@@ -0,0 +1,636 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::time::TimeUpdateStrategy;
+use art_fight::dino_run::obstacles::ObstacleCatalogue;
+use fastrand::Rng;
+use art_fight::dino_run::config::DinoRunConfig;
+use art_fight::dino_run::difficulty::min_fair_gap;
+use art_fight::dino_run::headless::headless_app;
+use art_fight::dino_run::input::{Binding, InputBindings};
+use art_fight::dino_run::mechanics::{
+    LevelSpeed, Obstacle, PHYSICS_HZ, Player, PlayerDied, PlayerHealth, PlayerHurt, PlayerJumps, PlayerScores,
+    player_jump_system, spawn_obstacle
+};
+use art_fight::dino_run::seed::RunSeed;
+use art_fight::dino_run::state::{DinoRunState, ResetRun};
+use art_fight::dino_run::stats::RunStats;
+
+// every headless update is one fixed step
+const STEPS_PER_SEC: u32 = PHYSICS_HZ as u32;
+
+#[derive(Resource, Default)]
+struct Counts {
+    jumps: usize,
+    hurts: usize,
+    scores: usize,
+    deaths: usize,
+    max_z: f32
+}
+
+fn count_events(
+    mut counts: ResMut<Counts>,
+    mut jumps: EventReader<PlayerJumps>,
+    mut hurts: EventReader<PlayerHurt>,
+    mut scores: EventReader<PlayerScores>,
+    mut deaths: EventReader<PlayerDied>,
+    player_query: Query<&Player>
+) {
+    counts.jumps += jumps.read().count();
+    counts.hurts += hurts.read().count();
+    counts.scores += scores.read().count();
+    counts.deaths += deaths.read().count();
+    if let Ok(player) = player_query.single() {
+        counts.max_z = counts.max_z.max(player.z);
+    };
+}
+
+// a run that has just started, the first generated obstacle is a few seconds away
+fn running_app() -> App {
+    let mut app = headless_app(RunSeed::fixed(1));
+    app.init_resource::<Counts>();
+    app.add_systems(Update, count_events);
+    app.world_mut().resource_mut::<NextState<DinoRunState>>().set(DinoRunState::Running);
+    app.update();
+    assert_eq!(*app.world().resource::<State<DinoRunState>>().get(), DinoRunState::Running);
+    app
+}
+
+fn key(app: &mut App, key_code: KeyCode, state: ButtonState) {
+    app.world_mut().send_event(KeyboardInput {
+        key_code,
+        logical_key: Key::Space,
+        state,
+        text: None,
+        repeat: false,
+        window: Entity::PLACEHOLDER
+    });
+}
+
+fn steps(app: &mut App, n: u32) {
+    for _ in 0..n {
+        app.update();
+    };
+}
+
+fn player(app: &mut App) -> Player {
+    let mut query = app.world_mut().query::<&Player>();
+    query.single(app.world()).unwrap().clone()
+}
+
+fn spawn_kind(app: &mut App, name: &str, x: f32) -> Entity {
+    let catalogue = app.world().resource::<ObstacleCatalogue>().clone();
+    let kind = catalogue.kinds.iter().position(|k| k.name == name).unwrap();
+    let world = app.world_mut();
+    let entity = spawn_obstacle(&mut world.commands(), &catalogue, kind, x, 0, &mut Rng::with_seed(0));
+    world.flush();
+    entity
+}
+
+fn spawn_crystal(app: &mut App, x: f32) -> Entity {
+    spawn_kind(app, "crystal", x)
+}
+
+// the jump and its max height, for a key held for `held_steps`
+fn jump_height(held_steps: u32) -> f32 {
+    let mut app = running_app();
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, held_steps);
+    key(&mut app, KeyCode::Space, ButtonState::Released);
+    steps(&mut app, STEPS_PER_SEC);
+    app.world().resource::<Counts>().max_z
+}
+
+#[test]
+fn one_jump_per_press() {
+    let mut app = running_app();
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    // held through the landing, which mustn't count as a second press
+    steps(&mut app, STEPS_PER_SEC * 2);
+    key(&mut app, KeyCode::Space, ButtonState::Released);
+    steps(&mut app, 4);
+    assert_eq!(app.world().resource::<Counts>().jumps, 1);
+}
+
+#[test]
+fn player_lands_on_the_ground() {
+    let mut app = running_app();
+    key(&mut app, KeyCode::ArrowUp, ButtonState::Pressed);
+    steps(&mut app, 2);
+    assert!(player(&mut app).airborne);
+    key(&mut app, KeyCode::ArrowUp, ButtonState::Released);
+    steps(&mut app, STEPS_PER_SEC);
+    let p = player(&mut app);
+    assert_eq!(p.z, 0.0);
+    assert_eq!(p.velocity, 0.0);
+    assert!(!p.airborne);
+}
+
+#[test]
+fn holding_jumps_higher_than_tapping() {
+    let tap = jump_height(1);
+    let hold = jump_height(STEPS_PER_SEC);
+    assert!(tap > 0.0);
+    assert!(hold > tap * 1.5, "held {} vs tapped {}", hold, tap);
+}
+
+#[test]
+fn grounded_player_is_hurt() {
+    let mut app = running_app();
+    spawn_crystal(&mut app, 2.0);
+    steps(&mut app, STEPS_PER_SEC);
+    let counts = app.world().resource::<Counts>();
+    assert_eq!(counts.hurts, 1);
+    assert_eq!(counts.scores, 0);
+}
+
+#[test]
+fn cleared_obstacle_scores_once() {
+    let mut app = running_app();
+    // at the starting speed this reaches the dino near the top of a held jump
+    spawn_crystal(&mut app, 2.25);
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, STEPS_PER_SEC * 2);
+    let counts = app.world().resource::<Counts>();
+    assert_eq!(counts.jumps, 1);
+    assert_eq!(counts.hurts, 0);
+    assert_eq!(counts.scores, 1);
+}
+
+#[test]
+fn obstacles_despawn_off_screen() {
+    let mut app = running_app();
+    let despawn_x = app.world().resource::<DinoRunConfig>().despawn_x;
+    let early = spawn_crystal(&mut app, despawn_x + 0.5);
+    steps(&mut app, STEPS_PER_SEC / 2);
+    assert!(app.world().get_entity(early).is_err());
+    // and the generated ones never linger past the despawn line
+    let mut query = app.world_mut().query::<&Obstacle>();
+    let mut seen = 0;
+    for _ in 0..STEPS_PER_SEC * 20 {
+        app.update();
+        for obstacle in query.iter(app.world()) {
+            assert!(obstacle.x >= despawn_x, "obstacle left at {}", obstacle.x);
+            seen = seen.max(obstacle.count + 1);
+        };
+    };
+    assert!(seen > 2);
+}
+
+// a held jump gets over a stalagmite, a tapped one doesn't, at a speed where they turn up
+fn jump_stalagmite(held_steps: u32) -> usize {
+    let mut app = running_app();
+    let speed = 8.0;
+    let curve = &mut app.world_mut().resource_mut::<DinoRunConfig>().difficulty;
+    curve.start_speed = speed;
+    curve.max_speed = speed;
+    spawn_kind(&mut app, "stalagmite", speed * 0.45);
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, held_steps);
+    key(&mut app, KeyCode::Space, ButtonState::Released);
+    // not so long that the first generated obstacle arrives
+    steps(&mut app, STEPS_PER_SEC - held_steps);
+    app.world().resource::<Counts>().hurts
+}
+
+#[test]
+fn stalagmites_need_a_held_jump() {
+    assert_eq!(jump_stalagmite(STEPS_PER_SEC), 0);
+    assert_eq!(jump_stalagmite(1), 1);
+}
+
+#[test]
+fn clusters_can_be_cleared() {
+    let mut app = running_app();
+    spawn_kind(&mut app, "cluster", 2.25);
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, STEPS_PER_SEC * 2);
+    let counts = app.world().resource::<Counts>();
+    assert_eq!(counts.hurts, 0);
+    assert_eq!(counts.scores, 1);
+}
+
+#[test]
+fn standing_dino_hits_a_stalactite() {
+    let mut app = running_app();
+    spawn_kind(&mut app, "stalactite", 2.0);
+    steps(&mut app, STEPS_PER_SEC);
+    assert_eq!(app.world().resource::<Counts>().hurts, 1);
+}
+
+#[test]
+fn ducking_clears_a_stalactite() {
+    let mut app = running_app();
+    spawn_kind(&mut app, "stalactite", 2.0);
+    key(&mut app, KeyCode::ArrowDown, ButtonState::Pressed);
+    steps(&mut app, STEPS_PER_SEC);
+    assert!(player(&mut app).ducking);
+    let counts = app.world().resource::<Counts>();
+    assert_eq!(counts.hurts, 0);
+    assert_eq!(counts.scores, 1);
+}
+
+#[test]
+fn ducking_mid_air_falls_faster() {
+    let airtime = |duck: bool| {
+        let mut app = running_app();
+        key(&mut app, KeyCode::Space, ButtonState::Pressed);
+        steps(&mut app, 8);
+        key(&mut app, KeyCode::Space, ButtonState::Released);
+        if duck {
+            key(&mut app, KeyCode::ControlLeft, ButtonState::Pressed);
+        };
+        let mut steps_taken = 8;
+        while player(&mut app).airborne {
+            steps(&mut app, 1);
+            steps_taken += 1;
+        };
+        steps_taken
+    };
+    assert!(airtime(true) < airtime(false));
+}
+
+#[test]
+fn jump_can_be_rebound() {
+    let mut app = running_app();
+    app.world_mut().resource_mut::<InputBindings>().jump = vec![Binding::Key(KeyCode::KeyJ)];
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, 4);
+    assert!(!player(&mut app).airborne);
+    key(&mut app, KeyCode::KeyJ, ButtonState::Pressed);
+    steps(&mut app, 4);
+    assert!(player(&mut app).airborne);
+}
+
+#[test]
+fn restart_starts_the_run_over() {
+    let mut app = running_app();
+    spawn_crystal(&mut app, 2.0);
+    steps(&mut app, STEPS_PER_SEC);
+    assert_eq!(app.world().resource::<RunStats>().hits, 1);
+    key(&mut app, KeyCode::KeyR, ButtonState::Pressed);
+    steps(&mut app, 1);
+    assert_eq!(*app.world().resource::<State<DinoRunState>>().get(), DinoRunState::Running);
+    assert_eq!(app.world().resource::<RunStats>().hits, 0);
+    let mut query = app.world_mut().query::<&Obstacle>();
+    assert_eq!(query.iter(app.world()).count(), 0);
+}
+
+// jumps for a press `early` steps before the first landing
+fn press_before_landing(early: u32) -> usize {
+    let mut app = running_app();
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, 1);
+    key(&mut app, KeyCode::Space, ButtonState::Released);
+    let mut airtime = 0;
+    while player(&mut app).airborne {
+        steps(&mut app, 1);
+        airtime += 1;
+    };
+    let mut app = running_app();
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, 1);
+    key(&mut app, KeyCode::Space, ButtonState::Released);
+    steps(&mut app, airtime - early);
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, 1);
+    key(&mut app, KeyCode::Space, ButtonState::Released);
+    steps(&mut app, early + 4);
+    app.world().resource::<Counts>().jumps
+}
+
+#[test]
+fn early_press_is_buffered_until_landing() {
+    let buffer = DinoRunConfig::default().jump_buffer;
+    let within = (buffer * STEPS_PER_SEC as f32) as u32 - 1;
+    assert_eq!(press_before_landing(2), 2);
+    assert_eq!(press_before_landing(within), 2);
+    assert_eq!(press_before_landing(within + 3), 1);
+}
+
+// jumps for a press `late` steps after the ground has dropped away under the dino
+fn press_after_falling(late: u32) -> usize {
+    let mut app = running_app();
+    steps(&mut app, 2);
+    let mut query = app.world_mut().query::<&mut Player>();
+    query.single_mut(app.world_mut()).unwrap().z = 1.0;
+    steps(&mut app, late);
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, 2);
+    app.world().resource::<Counts>().jumps
+}
+
+#[test]
+fn coyote_time_allows_a_late_jump() {
+    let coyote = DinoRunConfig::default().coyote_time;
+    let within = (coyote * STEPS_PER_SEC as f32) as u32 - 2;
+    assert_eq!(press_after_falling(0), 1);
+    assert_eq!(press_after_falling(within), 1);
+    assert_eq!(press_after_falling(within + 4), 0);
+}
+
+#[test]
+fn windows_can_be_turned_off() {
+    let mut app = running_app();
+    let mut config = app.world_mut().resource_mut::<DinoRunConfig>();
+    config.jump_buffer = 0.0;
+    config.coyote_time = 0.0;
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, 1);
+    key(&mut app, KeyCode::Space, ButtonState::Released);
+    // falling, just short of the ground, where the default buffer would have caught the press
+    while player(&mut app).velocity >= 0.0 || player(&mut app).z > 0.5 {
+        steps(&mut app, 1);
+    };
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, STEPS_PER_SEC);
+    assert_eq!(app.world().resource::<Counts>().jumps, 1);
+}
+
+#[test]
+fn tuning_changes_apply_mid_run() {
+    let mut app = running_app();
+    app.world_mut().resource_mut::<DinoRunConfig>().gravity *= 0.5;
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, STEPS_PER_SEC);
+    key(&mut app, KeyCode::Space, ButtonState::Released);
+    steps(&mut app, STEPS_PER_SEC);
+    let low_gravity = app.world().resource::<Counts>().max_z;
+    assert!(low_gravity > jump_height(STEPS_PER_SEC) * 1.5, "jumped {}", low_gravity);
+}
+
+#[test]
+fn bad_tuning_is_rejected() {
+    assert_eq!(DinoRunConfig::parse("()"), Ok(DinoRunConfig::default()));
+    assert_eq!(DinoRunConfig::parse("(gravity: 20.0)").map(|c| c.gravity), Ok(20.0));
+    assert!(DinoRunConfig::parse("(gravity: -1.0)").is_err());
+    assert!(DinoRunConfig::parse("(spawn_x: -10.0)").is_err());
+    assert!(DinoRunConfig::parse("(difficulty: (driver: Score, jitter: 1.5))").is_err());
+    assert!(DinoRunConfig::parse("(gravty: 20.0)").is_err());
+    assert!(DinoRunConfig::parse("(difficulty: (start_speed: 8.0, max_speed: 6.0))").is_err());
+}
+
+#[test]
+fn pausing_freezes_the_run() {
+    let mut app = running_app();
+    let crystal = spawn_crystal(&mut app, 6.0);
+    steps(&mut app, 4);
+    key(&mut app, KeyCode::Escape, ButtonState::Pressed);
+    steps(&mut app, 2);
+    key(&mut app, KeyCode::Escape, ButtonState::Released);
+    assert_eq!(*app.world().resource::<State<DinoRunState>>().get(), DinoRunState::Paused);
+    assert!(app.world().resource::<Time<Virtual>>().is_paused());
+    let x = app.world().get::<Obstacle>(crystal).unwrap().x;
+    let elapsed = app.world().resource::<RunStats>().elapsed;
+    steps(&mut app, STEPS_PER_SEC);
+    assert_eq!(app.world().get::<Obstacle>(crystal).unwrap().x, x);
+    assert_eq!(app.world().resource::<RunStats>().elapsed, elapsed);
+    key(&mut app, KeyCode::KeyP, ButtonState::Pressed);
+    steps(&mut app, 2);
+    assert_eq!(*app.world().resource::<State<DinoRunState>>().get(), DinoRunState::Running);
+    assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+    steps(&mut app, 4);
+    assert!(app.world().get::<Obstacle>(crystal).unwrap().x < x);
+}
+
+#[derive(Resource, Default)]
+struct Apex(f32);
+
+fn track_apex(
+    mut apex: ResMut<Apex>,
+    player_query: Query<&Player>
+) {
+    if let Ok(player) = player_query.single() {
+        apex.0 = apex.0.max(player.z);
+    };
+}
+
+// the top of a held jump, checked after every fixed step while the app updates `frame_hz` times a second
+fn held_jump_apex(frame_hz: f64) -> f32 {
+    let mut app = headless_app(RunSeed::fixed(1));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / frame_hz)));
+    app.init_resource::<Apex>();
+    app.add_systems(FixedUpdate, track_apex.after(player_jump_system));
+    app.world_mut().resource_mut::<NextState<DinoRunState>>().set(DinoRunState::Running);
+    app.update();
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, frame_hz as u32);
+    app.world().resource::<Apex>().0
+}
+
+#[test]
+fn jump_height_ignores_frame_rate() {
+    let slow = held_jump_apex(30.0);
+    let fast = held_jump_apex(240.0);
+    assert!(slow > 1.0);
+    assert_eq!(slow, fast);
+}
+
+fn state(app: &App) -> DinoRunState {
+    *app.world().resource::<State<DinoRunState>>().get()
+}
+
+fn game_over_app() -> App {
+    let mut app = running_app();
+    app.world_mut().resource_mut::<NextState<DinoRunState>>().set(DinoRunState::GameOver);
+    app.update();
+    assert_eq!(state(&app), DinoRunState::GameOver);
+    app
+}
+
+#[test]
+fn mashing_jump_doesnt_skip_game_over() {
+    let mut app = game_over_app();
+    for _ in 0..4 {
+        key(&mut app, KeyCode::Space, ButtonState::Pressed);
+        steps(&mut app, 2);
+        key(&mut app, KeyCode::Space, ButtonState::Released);
+        steps(&mut app, 2);
+    };
+    assert_eq!(state(&app), DinoRunState::GameOver);
+    steps(&mut app, STEPS_PER_SEC);
+    key(&mut app, KeyCode::Space, ButtonState::Pressed);
+    steps(&mut app, 2);
+    assert_eq!(state(&app), DinoRunState::Running);
+}
+
+#[test]
+fn restart_leaves_game_over_straight_away() {
+    let mut app = game_over_app();
+    key(&mut app, KeyCode::KeyR, ButtonState::Pressed);
+    steps(&mut app, 2);
+    assert_eq!(state(&app), DinoRunState::Running);
+}
+
+fn lives(app: &App) -> u8 {
+    app.world().resource::<PlayerHealth>().lives
+}
+
+#[test]
+fn second_hit_while_flashing_is_free() {
+    let mut app = running_app();
+    // reaches the dino a fifth of a second after the first, well inside flash_dur
+    spawn_crystal(&mut app, 2.0);
+    spawn_crystal(&mut app, 3.0);
+    steps(&mut app, STEPS_PER_SEC * 2);
+    assert_eq!(app.world().resource::<Counts>().hurts, 1);
+    assert_eq!(lives(&app), DinoRunConfig::default().max_lives - 1);
+}
+
+#[test]
+fn hit_after_the_flash_costs_a_life() {
+    let mut app = running_app();
+    spawn_crystal(&mut app, 2.0);
+    spawn_crystal(&mut app, 10.0);
+    steps(&mut app, STEPS_PER_SEC * 3);
+    assert_eq!(app.world().resource::<Counts>().hurts, 2);
+    assert_eq!(lives(&app), DinoRunConfig::default().max_lives - 2);
+}
+
+#[test]
+fn last_life_ends_the_run_once() {
+    let mut app = running_app();
+    let max_lives = DinoRunConfig::default().max_lives;
+    for i in 0..max_lives {
+        spawn_crystal(&mut app, 2.0 + 8.0 * i as f32);
+    };
+    steps(&mut app, STEPS_PER_SEC * (2 * max_lives as u32));
+    assert_eq!(lives(&app), 0);
+    assert_eq!(state(&app), DinoRunState::GameOver);
+    steps(&mut app, STEPS_PER_SEC);
+    assert_eq!(app.world().resource::<Counts>().deaths, 1);
+    assert_eq!(state(&app), DinoRunState::GameOver);
+}
+
+fn stats(app: &App) -> RunStats {
+    app.world().resource::<RunStats>().clone()
+}
+
+#[test]
+fn hit_breaks_the_streak_but_not_the_best() {
+    let mut app = running_app();
+    for _ in 0..3 {
+        app.world_mut().send_event(PlayerScores {x: -2.0});
+        steps(&mut app, 1);
+    };
+    let clean = stats(&app);
+    assert_eq!((clean.streak, clean.best_streak), (3, 3));
+    app.world_mut().send_event(PlayerHurt {x: 0.5});
+    steps(&mut app, 1);
+    let hit = stats(&app);
+    assert_eq!((hit.streak, hit.best_streak, hit.hits), (0, 3, 1));
+    app.world_mut().send_event(PlayerScores {x: -2.0});
+    steps(&mut app, 1);
+    let after = stats(&app);
+    assert_eq!((after.streak, after.best_streak, after.score()), (1, 3, 4));
+}
+
+#[test]
+fn distance_follows_the_level_speed() {
+    let mut app = running_app();
+    {
+        let mut config = app.world_mut().resource_mut::<DinoRunConfig>();
+        config.difficulty.max_speed = config.difficulty.start_speed;
+    };
+    steps(&mut app, 1);
+    let speed = app.world().resource::<LevelSpeed>().f32;
+    let before = stats(&app);
+    steps(&mut app, STEPS_PER_SEC * 2);
+    let after = stats(&app);
+    assert!((after.elapsed - before.elapsed - 2.0).abs() < 1e-4, "{}", after.elapsed - before.elapsed);
+    let travelled = after.distance - before.distance;
+    assert!((travelled - speed * 2.0).abs() < 1e-3, "{} at {}", travelled, speed);
+}
+
+#[test]
+fn stats_clear_on_reset() {
+    let mut app = running_app();
+    app.world_mut().send_event(PlayerScores {x: -2.0});
+    app.world_mut().send_event(PlayerHurt {x: 0.5});
+    steps(&mut app, STEPS_PER_SEC);
+    let before = stats(&app);
+    assert!(before.score() > 0 && before.hits > 0 && before.distance > 0.0);
+    app.world_mut().run_schedule(ResetRun);
+    let after = stats(&app);
+    assert_eq!((after.cleared, after.streak, after.best_streak, after.hits), (0, 0, 0, 0));
+    assert_eq!((after.distance, after.elapsed), (0.0, 0.0));
+}
+
+#[test]
+fn gaps_are_always_fair() {
+    let config = DinoRunConfig::default();
+    let curve = &config.difficulty;
+    let airtime = config.jump_airtime();
+    let mut clamped = 0;
+    for seed in 0..50 {
+        let mut rng = Rng::with_seed(seed);
+        for step in 0..=20 {
+            let t = step as f32 / 20.0;
+            let speed = curve.start_speed + (curve.max_speed - curve.start_speed) * t;
+            let stats = RunStats {elapsed: curve.ramp * t, ..RunStats::default()};
+            for _ in 0..20 {
+                let gap = curve.next_gap(&stats, speed, airtime, rng.f32());
+                let fair = min_fair_gap(speed, airtime);
+                assert!(gap >= fair, "gap {} under {} at speed {}", gap, fair, speed);
+                if gap == curve.tight_gap(speed, airtime) {
+                    clamped += 1;
+                };
+            };
+        };
+    };
+    // the jitter pulls plenty of gaps under the fair minimum, so the clamp has been tested too
+    assert!(clamped > 100, "only {} gaps were clamped", clamped);
+}
+
+#[derive(Resource, Default)]
+struct Spawned(Vec<(usize, f32)>);
+
+fn record_spawns(
+    mut spawned: ResMut<Spawned>,
+    query: Query<&Obstacle, Added<Obstacle>>
+) {
+    for obstacle in query {
+        spawned.0.push((obstacle.kind, obstacle.x));
+    };
+}
+
+// kind and spawn position of every obstacle in the first stretch of a run with no one at the controls
+fn obstacle_sequence(run_seed: RunSeed) -> Vec<(usize, f32)> {
+    let mut app = headless_app(run_seed);
+    app.init_resource::<Spawned>();
+    app.add_systems(Update, record_spawns);
+    app.world_mut().resource_mut::<NextState<DinoRunState>>().set(DinoRunState::Running);
+    app.update();
+    app.world_mut().resource_mut::<PlayerHealth>().lives = u8::MAX;
+    steps(&mut app, STEPS_PER_SEC * 30);
+    app.world_mut().remove_resource::<Spawned>().unwrap().0
+}
+
+#[test]
+fn same_seed_same_run() {
+    let first = obstacle_sequence(RunSeed::fixed(99));
+    assert!(first.len() > 10, "only {} obstacles", first.len());
+    assert_eq!(first, obstacle_sequence(RunSeed::fixed(99)));
+    assert_ne!(first, obstacle_sequence(RunSeed::fixed(100)));
+}
+
+#[test]
+fn only_random_seeds_reroll() {
+    let rolled = |run_seed: RunSeed| {
+        let mut app = headless_app(run_seed);
+        app.update();
+        app.world_mut().run_schedule(ResetRun);
+        app.world().resource::<RunSeed>().seed
+    };
+    let random = RunSeed::from_sources(None, None);
+    assert!(!random.fixed);
+    assert_ne!(rolled(random), random.seed);
+    let picked = RunSeed::from_sources(Some("42".to_string()), None);
+    assert_eq!(rolled(picked), 42);
+    let from_var = RunSeed::from_sources(None, Some(" 7 ".to_string()));
+    assert_eq!(rolled(from_var), 7);
+    // the command line wins, and a bad seed falls back to a random one
+    assert_eq!(RunSeed::from_sources(Some("1".to_string()), Some("2".to_string())).seed, 1);
+    assert!(!RunSeed::from_sources(Some("nope".to_string()), None).fixed);
+}
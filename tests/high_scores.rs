@@ -0,0 +1,76 @@
+use std::fs;
+use std::path::PathBuf;
+use art_fight::dino_run::high_scores::{HighScore, HighScores};
+
+// a fresh directory per test so they can run side by side
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("art_fight_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn entry(score: u32) -> HighScore {
+    HighScore {score, distance: score as f32 * 10.0, best_streak: score, date: 0, seed: None}
+}
+
+#[test]
+fn garbage_file_is_moved_aside() {
+    let dir = scratch_dir("garbage_scores");
+    let path = dir.join("scores.ron");
+    fs::write(&path, "this isn't ron {").unwrap();
+    let high_scores = HighScores::load(Some(path.clone()), 10);
+    assert!(high_scores.scores.is_empty());
+    assert!(!path.exists());
+    assert_eq!(fs::read_to_string(dir.join("scores.ron.bak")).unwrap(), "this isn't ron {");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn old_version_is_rejected() {
+    let dir = scratch_dir("old_scores");
+    let path = dir.join("scores.ron");
+    fs::write(&path, "(version: 0, scores: [(score: 5, distance: 50.0, best_streak: 5, date: 0, seed: None)])").unwrap();
+    let high_scores = HighScores::load(Some(path.clone()), 10);
+    assert!(high_scores.scores.is_empty());
+    assert!(dir.join("scores.ron.bak").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn scores_survive_a_save() {
+    let dir = scratch_dir("saved_scores");
+    let path = dir.join("scores.ron");
+    let mut high_scores = HighScores::load(Some(path.clone()), 10);
+    high_scores.insert(entry(3));
+    high_scores.insert(entry(7));
+    high_scores.save().unwrap();
+    let loaded = HighScores::load(Some(path), 10);
+    assert_eq!(loaded.scores, vec![entry(7), entry(3)]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn low_score_misses_a_full_table() {
+    let mut high_scores = HighScores::load(None, 3);
+    for score in [5, 6, 7] {
+        assert!(high_scores.insert(entry(score)).is_some());
+    };
+    assert_eq!(high_scores.insert(entry(4)), None);
+    assert_eq!(high_scores.insert(entry(8)), Some(0));
+    assert_eq!(high_scores.scores, vec![entry(8), entry(7), entry(6)]);
+}
+
+#[test]
+fn loaded_table_is_cut_to_capacity() {
+    let dir = scratch_dir("long_scores");
+    let path = dir.join("scores.ron");
+    let mut high_scores = HighScores::load(Some(path.clone()), 10);
+    for score in 1..=10 {
+        high_scores.insert(entry(score));
+    };
+    high_scores.save().unwrap();
+    let loaded = HighScores::load(Some(path), 4);
+    assert_eq!(loaded.scores, vec![entry(10), entry(9), entry(8), entry(7)]);
+    fs::remove_dir_all(&dir).unwrap();
+}
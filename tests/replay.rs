@@ -0,0 +1,80 @@
+use std::fs;
+use std::time::Duration;
+use bevy::time::TimeUpdateStrategy;
+use art_fight::dino_run::config::DinoRunConfig;
+use art_fight::dino_run::mechanics::PHYSICS_HZ;
+use art_fight::dino_run::headless::{autopilot_app, headless_app, run_to_game_over};
+use art_fight::dino_run::replay::{InputEvent, InputKind, Replay, ReplayPlayer, ReplaySettings};
+use art_fight::dino_run::seed::RunSeed;
+
+#[test]
+fn replay_bytes_round_trip() {
+    let replay = Replay {
+        seed: 0xDEAD_BEEF_1234,
+        config: Some(DinoRunConfig {jump_v: 11.5, max_lives: 5, ..DinoRunConfig::default()}),
+        end_tick: 70_000,
+        score: 42,
+        hits: 2,
+        events: vec![
+            InputEvent {tick: 0, kind: InputKind::Press},
+            InputEvent {tick: 0, kind: InputKind::Hold},
+            InputEvent {tick: 12, kind: InputKind::Release},
+            InputEvent {tick: 300, kind: InputKind::Duck},
+            InputEvent {tick: 69_999, kind: InputKind::Stand}  // a gap too big for one varint byte
+        ]
+    };
+    let bytes = replay.to_bytes();
+    assert_eq!(Replay::from_bytes(&bytes), Ok(replay));
+    assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Replay::from_bytes(b"nope").is_err());
+    let without_tuning = Replay {config: None, ..Replay::default()};
+    assert_eq!(Replay::from_bytes(&without_tuning.to_bytes()), Ok(without_tuning));
+}
+
+#[test]
+fn recorded_run_plays_back_the_same() {
+    let path = std::env::temp_dir().join(format!("art_fight_replay_{}.dinoreplay", std::process::id()));
+    let tuning = DinoRunConfig {jump_v: 11.0, gravity: 45.0, coyote_time: 0.0, ..DinoRunConfig::default()};
+    let mut app = autopilot_app(RunSeed::fixed(11), 0.3);
+    app.insert_resource(tuning.clone());
+    app.insert_resource(ReplaySettings {record: Some(path.clone()), replay: None});
+    let recorded = run_to_game_over(&mut app);
+    let replay = Replay::load(&path).unwrap();
+    assert_eq!((replay.score, replay.hits), (recorded.score(), recorded.hits));
+    assert!(!replay.events.is_empty());
+    assert_eq!(replay.config.as_ref(), Some(&tuning));
+
+    // no autopilot this time, the inputs come from the file alone, and a different seed and the
+    // default tuning are both overridden by the recording
+    let mut app = headless_app(RunSeed::fixed(12));
+    app.insert_resource(ReplaySettings {record: None, replay: Some(path.clone())});
+    let played = run_to_game_over(&mut app);
+    assert_eq!((played.score(), played.hits), (recorded.score(), recorded.hits));
+    assert!(app.world().resource::<ReplayPlayer>().matches(&played));
+    assert_eq!(app.world().resource::<DinoRunConfig>(), &tuning);
+    fs::remove_file(&path).unwrap();
+}
+
+// a slow frame runs several fixed steps in one update, the ones after the dino dies mustn't count
+#[test]
+fn steps_after_death_are_not_simulated() {
+    let record = |steps_per_update: u32, name: &str| {
+        let path = std::env::temp_dir().join(format!("art_fight_{}_{}.dinoreplay", name, std::process::id()));
+        let mut app = autopilot_app(RunSeed::fixed(21), 0.3);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(
+            Duration::from_secs_f64(steps_per_update as f64 / PHYSICS_HZ)));
+        app.insert_resource(ReplaySettings {record: Some(path.clone()), replay: None});
+        let stats = run_to_game_over(&mut app);
+        let replay = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        (stats, replay)
+    };
+    let (one, one_replay) = record(1, "one_step");
+    let (three, three_replay) = record(3, "three_steps");
+    assert_eq!(
+        (three.score(), three.hits, three.best_streak, three.distance, three.elapsed),
+        (one.score(), one.hits, one.best_streak, one.distance, one.elapsed)
+    );
+    assert_eq!(three_replay.end_tick, one_replay.end_tick);
+    assert_eq!(three_replay.events, one_replay.events);
+}